@@ -31,8 +31,8 @@ impl GuiState {
     }
 
     pub fn next_variation(&mut self) {
-        if let Ok(board) = self.game.get_board(self.board_number) {
-            if self.variation < board.get_variation_count()-1 {
+        if let Ok(node) = self.game.get_node(self.board_number) {
+            if self.variation + 1 < node.get_variation_count() {
                 self.variation += 1;
             }
             else {
@@ -42,11 +42,11 @@ impl GuiState {
     }
 
     pub fn prev_variation(&mut self) {
-        if let Ok(board) = self.game.get_board(self.board_number) {
+        if let Ok(node) = self.game.get_node(self.board_number) {
             if self.variation > 0 {
                 self.variation -= 1;
             } else {
-                self.variation = board.get_variation_count() - 1;
+                self.variation = node.get_variation_count().saturating_sub(1);
             }
         }
     }
@@ -57,8 +57,8 @@ impl GuiState {
     }
 
     pub fn next_board(&mut self) {
-        if let Ok(board) = self.game.get_board(self.board_number) {
-            if let Some(bn) = board.get_next(self.variation) {
+        if let Ok(node) = self.game.get_node(self.board_number) {
+            if let Some(bn) = node.get_child(self.variation) {
                 self.board_number = bn;
                 self.variation = 0;
             }
@@ -66,14 +66,16 @@ impl GuiState {
     }
 
     pub fn prev_board(&mut self) {
-        if let Ok(board) = self.game.get_board(self.board_number) {
-            self.board_number = board.get_prev();
+        if let Ok(node) = self.game.get_node(self.board_number) {
+            if let Some(bn) = node.get_parent() {
+                self.board_number = bn;
+            }
         }
     }
 
     pub fn last_board(&mut self) {
-        while let Ok(board) = self.game.get_board(self.board_number) {
-            if let Some(bn) = board.get_next(0) {
+        while let Ok(node) = self.game.get_node(self.board_number) {
+            if let Some(bn) = node.get_child(0) {
                 self.board_number = bn;
             } else {
                 return;
@@ -83,8 +85,8 @@ impl GuiState {
 
     pub fn last_move_number(&mut self) -> u32 {
         let mut bn = self.board_number;
-        while let Ok(board) = self.game.get_board(bn) {
-            match board.get_next(0) {
+        while let Ok(node) = self.game.get_node(bn) {
+            match node.get_child(0) {
                 Some(n) => bn = n,
                 _ => return bn,
             }
@@ -108,7 +110,7 @@ fn create_display(event_loop: &glutin::event_loop::EventLoop<()>) -> glium::Disp
         .with_stencil_buffer(0)
         .with_vsync(true);
 
-    glium::Display::new(window_builder, context_builder, &event_loop).unwrap()
+    glium::Display::new(window_builder, context_builder, event_loop).unwrap()
 }
 
 fn main() -> Result<(), go::Error> {
@@ -131,7 +133,7 @@ fn main() -> Result<(), go::Error> {
         .ok_or(go::Error::Other("No file parameter".to_string()))?;
 
     let event_loop = glutin::event_loop::EventLoop::with_user_event();
-    let display = create_display(&&event_loop);
+    let display = create_display(&event_loop);
 
     let mut egui = egui_glium::EguiGlium::new(&display);
 
@@ -229,6 +231,9 @@ fn main() -> Result<(), go::Error> {
                     ui.label(gui_state.game().get_rank_white());
                     ui.label(" )");
                 });
+                if let Ok(board) = gui_state.get_board() {
+                    ui.label(format!("Captures: {}", board.get_prisoners_white()));
+                }
                 ui.add(egui::widgets::Separator::default().spacing(20.0));
 
                 ui.horizontal(|ui| {
@@ -238,6 +243,9 @@ fn main() -> Result<(), go::Error> {
                     ui.label(gui_state.game().get_rank_black());
                     ui.label(" )");
                 });
+                if let Ok(board) = gui_state.get_board() {
+                    ui.label(format!("Captures: {}", board.get_prisoners_black()));
+                }
                 ui.add(egui::widgets::Separator::default().spacing(20.0));
 
                 ui.horizontal(|ui| {
@@ -258,6 +266,18 @@ fn main() -> Result<(), go::Error> {
                     ui.label(gui_state.last_move_number().to_string());
                 });
 
+                if let Ok(node) = gui_state.game().get_node(gui_state.get_board_number()) {
+                    if node.get_name().is_some() || node.get_comment().is_some() {
+                        ui.add(egui::widgets::Separator::default().spacing(20.0));
+                    }
+                    if let Some(name) = node.get_name() {
+                        ui.heading(name);
+                    }
+                    if let Some(comment) = node.get_comment() {
+                        ui.label(comment);
+                    }
+                }
+
                 //                ui.spacing_mut().slider_width = 280.0;
                 //                ui.add(
                 //                    egui::Slider::new(&mut board_number, 0..=game.get_final_move_number())
@@ -295,56 +315,46 @@ fn main() -> Result<(), go::Error> {
                 ts.render(&mut target, &board_tex, display_dim);
 
                 let board = gui_state.get_board().unwrap();
-
-                for r in 0..board.get_size() {
-                    for c in 0..board.get_size() {
-                        if let Ok(p) = board.get_point(r, c) {
-                            match p {
-                                go::PointState::Filled {
-                                    move_number,
-                                    stone_color,
-                                } => match stone_color {
-                                    Color::White => ss.render(
-                                        &mut target,
-                                        &white_stone_tex,
-                                        display_dim,
-                                        r,
-                                        c,
-                                        stone_color,
-                                        gui_state.get_board_number() > 0
-                                            && board.get_last_move().get_number() == move_number,
-                                    ),
-                                    Color::Black => ss.render(
-                                        &mut target,
-                                        &black_stone_tex,
-                                        display_dim,
-                                        r,
-                                        c,
-                                        stone_color,
-                                        gui_state.get_board_number() > 0
-                                            && board.get_last_move().get_number() == move_number,
-                                    ),
-                                },
-                                _ => {}
-                            }
+                let node = gui_state.game().get_node(gui_state.get_board_number()).unwrap();
+                let last_move_number = node.get_move().map(|m| m.get_number());
+
+                for r in 0..board.get_height() {
+                    for c in 0..board.get_width() {
+                        if let Ok(go::PointState::Filled {
+                            move_number,
+                            stone_color,
+                        }) = board.get_point(r, c)
+                        {
+                            let stone_tex = match stone_color {
+                                Color::White => &white_stone_tex,
+                                Color::Black => &black_stone_tex,
+                            };
+                            ss.render(
+                                &mut target,
+                                stone_tex,
+                                display_dim,
+                                r,
+                                c,
+                                stone_color,
+                                last_move_number == Some(move_number),
+                            );
                         }
                     }
                 }
 
-                let next_boards = board.get_next_boards();
-                let mut i = 0;
-                for board_number in next_boards {
-                    if let Ok(b) = gui_state.game().get_board(board_number) {
-                        let m = b.get_last_move();
-                        ns.render(
-                            &mut target,
-                            display_dim,
-                            m.row(),
-                            m.col(),
-                            m.get_color(),
-                            i == gui_state.get_variation(),
-                        );
-                        i += 1;
+                for (i, child) in node.get_children().into_iter().enumerate() {
+                    // A node that only sets up stones has no move to mark.
+                    if let Ok(Some(m)) = gui_state.game().get_node(child).map(|n| n.get_move()) {
+                        if let Some(intersection) = m.get_intersection() {
+                            ns.render(
+                                &mut target,
+                                display_dim,
+                                intersection.row(),
+                                intersection.col(),
+                                m.get_color(),
+                                i as u32 == gui_state.get_variation(),
+                            );
+                        }
                     }
                 }
 
@@ -1,5 +1,3 @@
-#[cfg(dev)]
-use glium;
 use glium::{implement_vertex, uniform, Surface};
 use sgf_parser::*;
 
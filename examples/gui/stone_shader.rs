@@ -1,5 +1,3 @@
-#[cfg(dev)]
-use glium;
 use glium::{implement_vertex, uniform, Surface};
 use sgf_parser::Color;
 
@@ -115,6 +113,7 @@ impl Shader {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn render(
         &mut self,
         target: &mut glium::Frame,
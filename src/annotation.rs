@@ -0,0 +1,73 @@
+/// How strongly an annotation applies, from the SGF double value 1 or 2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Emphasis {
+    #[default]
+    Normal,
+    Strong,
+}
+
+impl Emphasis {
+    // Any value but "2" is taken as normal, as the SGF spec allows an empty value.
+    pub(crate) fn from_sgf(value: &str) -> Emphasis {
+        match value {
+            "2" => Emphasis::Strong,
+            _ => Emphasis::Normal,
+        }
+    }
+}
+
+/// A judgement of the move played at a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveAnnotation {
+    /// BM
+    BadMove(Emphasis),
+    /// TE
+    Tesuji(Emphasis),
+    /// DO
+    Doubtful,
+    /// IT
+    Interesting,
+}
+
+impl MoveAnnotation {
+    pub(crate) fn from_sgf(ident: &str, value: &str) -> Option<MoveAnnotation> {
+        match ident {
+            "BM" => Some(MoveAnnotation::BadMove(Emphasis::from_sgf(value))),
+            "TE" => Some(MoveAnnotation::Tesuji(Emphasis::from_sgf(value))),
+            "DO" => Some(MoveAnnotation::Doubtful),
+            "IT" => Some(MoveAnnotation::Interesting),
+            _ => None,
+        }
+    }
+
+    /// Whether the move is marked bad or doubtful.
+    pub fn is_mistake(&self) -> bool {
+        matches!(self, MoveAnnotation::BadMove(_) | MoveAnnotation::Doubtful)
+    }
+}
+
+/// A judgement of the position at a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionAnnotation {
+    /// GB
+    GoodForBlack(Emphasis),
+    /// GW
+    GoodForWhite(Emphasis),
+    /// DM
+    Even(Emphasis),
+    /// UC
+    Unclear(Emphasis),
+}
+
+impl PositionAnnotation {
+    pub(crate) fn from_sgf(ident: &str, value: &str) -> Option<PositionAnnotation> {
+        let emphasis = Emphasis::from_sgf(value);
+        match ident {
+            "GB" => Some(PositionAnnotation::GoodForBlack(emphasis)),
+            "GW" => Some(PositionAnnotation::GoodForWhite(emphasis)),
+            "DM" => Some(PositionAnnotation::Even(emphasis)),
+            "UC" => Some(PositionAnnotation::Unclear(emphasis)),
+            _ => None,
+        }
+    }
+}
@@ -1,6 +1,5 @@
 use clap::{App, Arg};
 
-use go;
 use anyhow::Result;
 use go::Error;
 
@@ -16,12 +15,40 @@ fn main() -> Result<(), Error> {
                 .takes_value(true)
                 .help("SGF file name"),
         )
+        .arg(
+            Arg::with_name("comments")
+                .short("m")
+                .long("comments")
+                .help("Print the commentary of each move after the board"),
+        )
+        .arg(
+            Arg::with_name("coordinates")
+                .short("c")
+                .long("coordinates")
+                .help("Label rows and columns"),
+        )
         .get_matches();
 
     let file_name = matches.value_of("file").map(|f| f.to_string()).ok_or(Error::Other("No file parameter".to_string()))?;
     let game = go::Game::from_sgf_file(file_name)?;
-    let board = game.get_board(game.get_final_move_number()).unwrap();
-    println!("{}", board.to_ascii());
+    let last = game.get_main_line_end();
+    let board = game.get_board(last).unwrap();
+    let markup = game.get_markup(last)?;
+    println!("{}", board.to_ascii_with_markup(&markup, matches.is_present("coordinates")));
+    if matches.is_present("comments") {
+        for node in game.get_line(last)? {
+            let node = game.get_node(node)?;
+            if let Some(comment) = node.get_comment() {
+                let label = match (node.get_move(), node.get_name()) {
+                    (Some(m), Some(name)) => format!("Move {} ({}): ", m.get_number(), name),
+                    (Some(m), None) => format!("Move {}: ", m.get_number()),
+                    (None, Some(name)) => format!("{}: ", name),
+                    (None, None) => String::new(),
+                };
+                println!("{}{}", label, comment);
+            }
+        }
+    }
     Ok(())
 }
 
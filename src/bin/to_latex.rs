@@ -1,6 +1,5 @@
 use clap::{App, Arg};
 
-use go;
 use anyhow::Result;
 use go::Error;
 
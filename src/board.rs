@@ -7,123 +7,203 @@ use std::collections::BTreeSet;
 use std::collections::HashSet;
 
 use super::color_to_string;
+use super::column_label;
 use super::Error;
+use super::Group;
 use super::Intersection;
+use super::Markup;
 use super::Move;
 use super::PointState;
+use super::Ruleset;
+use super::SuicideRule;
 
-#[derive(Debug, Clone)]
-enum GroupPoint {
-    Ungrouped,
-    Grouped { group_number: i32 },
+// Zobrist key for a stone of the given colour on the given point. Keys are
+// derived with splitmix64 so every board of every size shares the same table
+// without having to store one.
+fn zobrist_key(intersection: Intersection, stone_color: Color) -> u64 {
+    let index = (intersection.row as u64 * 64 + intersection.col as u64) * 2
+        + match stone_color {
+            Color::Black => 0,
+            Color::White => 1,
+        };
+    let mut z = index.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
 }
 
+// igo has no coordinates past column z, see column_label.
+const MAX_LATEX_WIDTH: u32 = 25;
+
 #[derive(Debug, Clone)]
 pub struct Board {
-    size: u32,
+    width: u32,
+    height: u32,
     points: array2d::Array2D<PointState>,
-    prev_board: u32,
-    next_board: Vec<u32>,
     move_number: u32,
     moves: BTreeMap<u32, Move>,
+    ko_point: Option<(Intersection, Color)>,
+    hash: u64,
+    ruleset: Ruleset,
+    prisoners_black: u32,
+    prisoners_white: u32,
+    setup: bool,
+    to_play: Option<Color>,
 }
 
 impl Board {
     pub fn new(size: u32) -> Board {
+        Board::with_dimensions(size, size)
+    }
+
+    /// An empty board width columns wide and height rows high.
+    pub fn with_dimensions(width: u32, height: u32) -> Board {
         Board {
-            size,
-            points: array2d::Array2D::filled_with(PointState::Empty, size as usize, size as usize),
-            prev_board: 0,
-            next_board: vec![],
+            width,
+            height,
+            points: array2d::Array2D::filled_with(PointState::Empty, height as usize, width as usize),
             move_number: 0,
             moves: BTreeMap::new(),
+            ko_point: None,
+            hash: 0,
+            ruleset: Ruleset::default(),
+            prisoners_black: 0,
+            prisoners_white: 0,
+            setup: false,
+            to_play: None,
         }
     }
 
-    pub fn get_size(&self) -> u32 { self.size }
+    /// The width of the board, which is also its height unless it is rectangular.
+    pub fn get_size(&self) -> u32 { self.width }
+
+    /// The number of columns.
+    pub fn get_width(&self) -> u32 { self.width }
 
-    pub fn set_prev(&mut self, board_number: u32) {
-        self.prev_board = board_number;
+    /// The number of rows.
+    pub fn get_height(&self) -> u32 { self.height }
+
+    pub fn is_square(&self) -> bool {
+        self.width == self.height
     }
 
-    pub fn get_prev(&self) -> u32 {
-        self.prev_board
+    pub fn get_ruleset(&self) -> Ruleset {
+        self.ruleset.clone()
     }
 
-    pub fn clear_next(&mut self) {
-        self.next_board.clear();
+    pub fn set_ruleset(&mut self, ruleset: Ruleset) {
+        self.ruleset = ruleset;
     }
 
-    pub fn add_next(&mut self, board_number: u32) {
-        self.next_board.push(board_number);
+    /// Whether this position was made by setup properties (AB, AW, AE) part
+    /// way through the game rather than by a move. It keeps the move number
+    /// and last move of the position before it. The root's setup is the
+    /// starting position and does not count.
+    pub fn is_setup(&self) -> bool {
+        self.setup
     }
 
-    pub fn get_next(&self, variation: u32) -> Option<u32> {
-        if self.next_board.len() > variation as usize {
-            Some(self.next_board[variation as usize])
-        }
-        else {
-            None
+    pub fn set_setup(&mut self, setup: bool) {
+        self.setup = setup;
+    }
+
+    /// The player to move: the one set with set_to_play if any, otherwise the
+    /// opponent of whoever made the last move, and black at the start.
+    pub fn to_play(&self) -> Color {
+        match (self.to_play, self.moves.get(&self.move_number)) {
+            (Some(color), _) => color,
+            (None, Some(last_move)) => !last_move.color,
+            (None, None) => Color::Black,
         }
     }
 
-    pub fn get_next_boards(&self) -> Vec<u32> {
-        self.next_board.clone()
+    /// Sets who plays next in this position, as with the SGF PL property, or
+    /// None to go back to deriving it. A move or pass clears it.
+    pub fn set_to_play(&mut self, color: Option<Color>) {
+        self.to_play = color;
     }
 
-    pub fn get_last_move(&self) -> Move {
-        self.moves[&self.move_number].clone()
+    pub(crate) fn explicit_to_play(&self) -> Option<Color> {
+        self.to_play
     }
 
-    pub fn get_variation_count(&self) -> u32 {
-        self.next_board.len() as u32
+    /// The number of moves, passes included, played to reach this position.
+    pub fn get_move_number(&self) -> u32 {
+        self.move_number
+    }
+
+    /// The move that led to this position, None before the first move.
+    pub fn get_last_move(&self) -> Option<Move> {
+        self.moves.get(&self.move_number).cloned()
     }
 
     pub fn get_point(&self, r: u32, c: u32) -> Result<PointState, Error> {
-        if let Some(p) = self.points.get(r as usize, c as usize).clone() {
+        if let Some(p) = self.points.get(r as usize, c as usize) {
             Ok(p.clone())
         } else {
             Err(Error::InvalidBoardNumber("No Point".to_string()))
         }
     }
 
+    /// The position as an igo diagram. igo columns run from a to z without
+    /// i, so boards wider than 25 columns are an `Error::Other`.
     pub fn render_diagram(
         &self,
         from_move: Option<u32>,
     ) -> Result<String, Error> {
+        self.render_diagram_with_markup(from_move, &Markup::default())
+    }
+
+    /// Like render_diagram, drawing circles, squares, triangles, crosses
+    /// and labels (CR, SQ, TR, MA and LB) on the board and listing arrows and
+    /// lines below it. Selected (SL) and dimmed (DD) points are not drawn.
+    pub fn render_diagram_with_markup(
+        &self,
+        from_move: Option<u32>,
+        markup: &Markup,
+    ) -> Result<String, Error> {
+        if self.width > MAX_LATEX_WIDTH {
+            return Err(Error::Other(format!(
+                "LaTeX diagrams are at most {} columns wide, not {}",
+                MAX_LATEX_WIDTH, self.width
+            )));
+        }
         let mut ret: String = "".to_string();
         let mut cap_ret: String = "".to_string();
         let mut max_move: u32 = 0;
         let mut numbered_moves: HashSet<u32> = HashSet::new();
         let mut captured_moves: BTreeMap<Intersection, BTreeSet<u32>> = BTreeMap::new();
+        let mut passes: Vec<u32> = vec![];
 
-        for c in 0..self.size {
-            for r in 0..self.size {
+        for c in 0..self.width {
+            for r in 0..self.height {
                 match self.points.get(r as usize, c as usize) {
                     Some(&PointState::Filled {
                         move_number: 0,
-                        stone_color: _,
+                        stone_color,
                     }) => {
-                        ret += &r#"\black{"#.to_string();
+                        ret += r#"\"#;
+                        ret += &color_to_string(&stone_color);
+                        ret += "{";
                         ret += &Intersection::new(r, c).to_coord();
-                        ret += &"}\n";
+                        ret += "}\n";
                     }
 
                     Some(&PointState::Filled {
                         move_number,
                         stone_color,
                     }) => {
-                        ret += &r#"\"#.to_string();
+                        ret += r#"\"#;
                         ret += &color_to_string(&stone_color);
                         if move_number >= from_move.unwrap_or(0) {
-                            ret += &"[";
+                            ret += "[";
                             ret += &move_number.to_string();
-                            ret += &"]";
+                            ret += "]";
                             numbered_moves.insert(move_number);
                         }
-                        ret += &"{";
+                        ret += "{";
                         ret += &Intersection::new(r, c).to_coord();
-                        ret += &"}\n";
+                        ret += "}\n";
                         if move_number > max_move {
                             max_move = move_number;
                         }
@@ -136,19 +216,27 @@ impl Board {
         for move_num in from_move.unwrap_or(0)..max_move {
             if move_num > 0 && !numbered_moves.contains(&move_num) {
                 if let Some(m) = &self.moves.get(&move_num) {
-                    captured_moves
-                        .entry(m.intersection)
-                        .or_insert_with(BTreeSet::new)
-                        .insert(move_num);
+                    match m.intersection {
+                        Some(intersection) => {
+                            captured_moves
+                                .entry(intersection)
+                                .or_default()
+                                .insert(move_num);
+                        }
+                        None => passes.push(move_num),
+                    }
                 }
             }
         }
 
         alphabet!(CAPS = "ABCDEFGHIJKLMNOPQRST");
         let mut caps = CAPS.iter_words_counting();
+        // Capture letters go round the labels already on the board.
+        let labels: HashSet<String> = markup.get_labels().into_iter().map(|(_, label)| label).collect();
+        let mut lettered: HashSet<Intersection> = HashSet::new();
         for (intersection, move_list) in &captured_moves {
             cap_ret += &move_list.iter().join(", ");
-            cap_ret += &" at ";
+            cap_ret += " at ";
             if let Some(&PointState::Filled {
                 move_number,
                 stone_color: _,
@@ -156,22 +244,62 @@ impl Board {
             {
                 cap_ret += &move_number.to_string();
             } else {
-                let loc = caps.next().unwrap_or("Z".to_string());
+                let loc = caps.find(|loc| !labels.contains(loc)).unwrap_or("Z".to_string());
                 cap_ret += &loc;
+                lettered.insert(*intersection);
 
-                ret += &r#"\gobansymbol{"#.to_string();
+                ret += r#"\gobansymbol{"#;
                 ret += &intersection.to_coord();
-                ret += &"}{";
+                ret += "}{";
                 ret += &loc;
-                ret += &"}\n";
+                ret += "}\n";
             };
-            cap_ret += &"\\\\\n";
+            cap_ret += "\\\\\n";
+        }
+        for move_num in passes {
+            cap_ret += &move_num.to_string();
+            cap_ret += " pass\\\\\n";
         }
 
-        ret += &"\n";
+        // Numbered stones and capture letters take precedence over markup.
+        for c in 0..self.width {
+            for r in 0..self.height {
+                let intersection = Intersection::new(r, c);
+                let numbered = matches!(
+                    self.points.get(r as usize, c as usize),
+                    Some(PointState::Filled { move_number, .. }) if numbered_moves.contains(move_number)
+                );
+                if numbered || lettered.contains(&intersection) {
+                    continue;
+                }
+                if let Some(symbol) = markup.latex_symbol(intersection) {
+                    ret += r#"\gobansymbol{"#;
+                    ret += &intersection.to_coord();
+                    ret += "}{";
+                    ret += &symbol;
+                    ret += "}\n";
+                }
+            }
+        }
+        for (kind, pairs) in [("Arrow", markup.get_arrows()), ("Line", markup.get_lines())] {
+            for (from, to) in pairs {
+                cap_ret += &format!("{} from {} to {}\\\\\n", kind, from.to_coord(), to.to_coord());
+            }
+        }
+
+        ret += "\n";
         ret += "\\begin{center}\n";
         ret += "\\vflipgoban\n";
-        ret += "\\shortstack{\\showfullgoban \\\\ From move ";
+        ret += "\\shortstack{";
+        if self.is_square() && self.width <= 19 {
+            ret += "\\showfullgoban";
+        } else {
+            // Only show the part of the goban this board covers.
+            ret += "\\showgoban[a1,";
+            ret += &Intersection::new(self.height - 1, self.width - 1).to_coord();
+            ret += "]";
+        }
+        ret += " \\\\ From move ";
         ret += &from_move.unwrap_or(0).to_string();
         ret += "}\n";
         ret += "\\end{center}\n";
@@ -197,7 +325,9 @@ impl Board {
                         },
                     )
                     .ok();
-                self.remove_captures(stone_color).ok();
+                self.hash ^= zobrist_key(intersection, stone_color);
+                self.remove_captures(intersection, stone_color);
+                self.ko_point = None;
                 Ok(())
             }
             Some(&PointState::Filled {
@@ -212,11 +342,15 @@ impl Board {
         &mut self,
         intersection: Intersection,
         stone_color: Color,
-    ) -> Result<(), Error> {
+    ) -> Result<Vec<Intersection>, Error> {
+        if let Some((ko_point, ko_color)) = self.ko_point {
+            if ko_point == intersection && ko_color == stone_color {
+                return Err(Error::KoViolation(intersection.to_coord()));
+            }
+        }
         match self.points.get(intersection.row as usize, intersection.col as usize) {
             Some(&PointState::Empty) => {
                 self.move_number += 1;
-                self.moves.insert(self.move_number, Move { move_number: self.move_number, intersection, color: stone_color });
                 self.points
                     .set(
                         intersection.row as usize,
@@ -227,8 +361,57 @@ impl Board {
                         },
                     )
                     .ok();
-                self.remove_captures(stone_color).ok();
-                Ok(())
+                self.hash ^= zobrist_key(intersection, stone_color);
+                let mut captures = self.remove_captures(intersection, stone_color);
+                let (group_members, group_liberties) = self.group(intersection);
+                let ko_point = if captures.len() == 1
+                    && group_members.len() == 1
+                    && group_liberties.len() == 1
+                    && group_liberties.contains(&captures[0])
+                {
+                    // A single stone captured a single stone and its only
+                    // liberty is the captured point, so retaking is ko.
+                    Some((captures[0], !stone_color))
+                } else {
+                    None
+                };
+                self.add_prisoners(stone_color, captures.len() as u32);
+                if group_liberties.is_empty() {
+                    match self.ruleset.get_suicide_rule() {
+                        SuicideRule::Forbidden => {
+                            // Nothing was captured, so undoing the stone restores the board.
+                            self.points
+                                .set(intersection.row as usize, intersection.col as usize, PointState::Empty)
+                                .ok();
+                            self.hash ^= zobrist_key(intersection, stone_color);
+                            self.move_number -= 1;
+                            return Err(Error::InvalidMove("Suicide".to_string()));
+                        }
+                        SuicideRule::Allowed => {
+                            self.add_prisoners(!stone_color, group_members.len() as u32);
+                            for member in group_members {
+                                self.points
+                                    .set(member.row as usize, member.col as usize, PointState::Empty)
+                                    .ok();
+                                self.hash ^= zobrist_key(member, stone_color);
+                                captures.push(member);
+                            }
+                        }
+                    }
+                }
+                self.ko_point = ko_point;
+                self.setup = false;
+                self.to_play = None;
+                self.moves.insert(
+                    self.move_number,
+                    Move {
+                        move_number: self.move_number,
+                        intersection: Some(intersection),
+                        color: stone_color,
+                        captures: captures.clone(),
+                    },
+                );
+                Ok(captures)
             }
             Some(&PointState::Filled {
                 move_number: _,
@@ -238,179 +421,326 @@ impl Board {
         }
     }
 
-    fn remove_captures(&mut self, placed_stone_color: Color) -> Result<(), String> {
-        let mut group_assignments: array2d::Array2D<GroupPoint> =
-            array2d::Array2D::filled_with(GroupPoint::Ungrouped, self.size as usize, self.size as usize);
-        let mut group_liberties: HashSet<Intersection> = HashSet::new();
-        let mut group_members: HashSet<Intersection> = HashSet::new();
-        let mut group_number = 1;
-        for r in 0..self.size {
-            for c in 0..self.size {
-                match self.points.get(r as usize, c as usize) {
-                    Some(&PointState::Filled {
-                        move_number: _,
-                        stone_color,
-                    }) => {
-                        if let Ok(result) = self.check_point(
-                            &mut group_assignments,
-                            &mut group_liberties,
-                            &mut group_members,
-                            stone_color,
-                            Intersection::new(r, c),
-                            group_number,
-                        ) {
-                            if result {
-                                if group_liberties.len() == 0 && placed_stone_color != stone_color {
-                                    for intersection in &group_members {
-                                        self.points
-                                            .set(
-                                                intersection.row as usize,
-                                                intersection.col as usize,
-                                                PointState::Empty,
-                                            )
-                                            .or(Err(Error::InvalidBoardNumber(
-                                                "Could not set point".to_string(),
-                                            )))
-                                            .ok();
-                                    }
-                                }
-                                group_number += 1;
-                                group_liberties.clear();
-                                group_members.clear();
-                            }
-                        }
+    /// Whether stone_color may play on intersection: the point must be empty,
+    /// not a ko recapture and, unless suicide is allowed, must leave the new
+    /// stone with a liberty. Agrees with `place_stone` without modifying the board.
+    /// Superko is a property of the game record and is checked by `Game`.
+    pub fn is_legal(&self, intersection: Intersection, stone_color: Color) -> bool {
+        match self.points.get(intersection.row as usize, intersection.col as usize) {
+            Some(&PointState::Empty) => {}
+            _ => return false,
+        }
+        if self.ko_point == Some((intersection, stone_color)) {
+            return false;
+        }
+        if self.ruleset.get_suicide_rule() == SuicideRule::Allowed {
+            return true;
+        }
+        self.neighbours(intersection).into_iter().any(|neighbour| {
+            match self.points.get(neighbour.row as usize, neighbour.col as usize) {
+                Some(&PointState::Empty) => true,
+                // Connecting to a friendly group that keeps another liberty,
+                // or capturing an enemy group in atari, leaves a liberty.
+                Some(&PointState::Filled {
+                    move_number: _,
+                    stone_color: neighbour_color,
+                }) => (self.get_liberty_count(neighbour) > 1) == (neighbour_color == stone_color),
+                None => false,
+            }
+        })
+    }
+
+    /// Every intersection where stone_color may legally play, in row then column order.
+    pub fn legal_moves(&self, stone_color: Color) -> Vec<Intersection> {
+        let mut ret: Vec<Intersection> = vec![];
+        for r in 0..self.height {
+            for c in 0..self.width {
+                let intersection = Intersection::new(r, c);
+                if self.is_legal(intersection, stone_color) {
+                    ret.push(intersection);
+                }
+            }
+        }
+        ret
+    }
+
+    /// Records a pass by stone_color. Passing lifts any ko restriction.
+    pub fn pass(&mut self, stone_color: Color) {
+        self.move_number += 1;
+        self.setup = false;
+        self.to_play = None;
+        self.moves.insert(
+            self.move_number,
+            Move {
+                move_number: self.move_number,
+                intersection: None,
+                color: stone_color,
+                captures: vec![],
+            },
+        );
+        self.ko_point = None;
+    }
+
+    /// White stones captured by black, including white's suicides.
+    pub fn get_prisoners_black(&self) -> u32 {
+        self.prisoners_black
+    }
+
+    /// Black stones captured by white, including black's suicides.
+    pub fn get_prisoners_white(&self) -> u32 {
+        self.prisoners_white
+    }
+
+    fn add_prisoners(&mut self, capturing_color: Color, count: u32) {
+        match capturing_color {
+            Color::Black => self.prisoners_black += count,
+            Color::White => self.prisoners_white += count,
+        }
+    }
+
+    /// The point the next player may not play on because it would immediately retake a ko.
+    pub fn get_ko_point(&self) -> Option<Intersection> {
+        self.ko_point.map(|(intersection, _)| intersection)
+    }
+
+    pub(crate) fn clear_ko_point(&mut self) {
+        self.ko_point = None;
+    }
+
+    /// Zobrist hash of the stones on the board, used for superko detection.
+    pub fn get_hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// The group with a stone on intersection, or None if the point is empty.
+    pub fn get_group(&self, intersection: Intersection) -> Option<Group> {
+        let color = match self.points.get(intersection.row as usize, intersection.col as usize) {
+            Some(&PointState::Filled {
+                move_number: _,
+                stone_color,
+            }) => stone_color,
+            _ => return None,
+        };
+        let (group_members, group_liberties) = self.group(intersection);
+        Some(Group {
+            color,
+            stones: group_members.into_iter().collect(),
+            liberties: group_liberties.into_iter().collect(),
+        })
+    }
+
+    /// Every group on the board, in row then column order of their first stone.
+    pub fn get_groups(&self) -> Vec<Group> {
+        let mut ret: Vec<Group> = vec![];
+        let mut grouped: HashSet<Intersection> = HashSet::new();
+        for r in 0..self.height {
+            for c in 0..self.width {
+                let intersection = Intersection::new(r, c);
+                if grouped.contains(&intersection) {
+                    continue;
+                }
+                if let Some(group) = self.get_group(intersection) {
+                    grouped.extend(group.stones.iter().cloned());
+                    ret.push(group);
+                }
+            }
+        }
+        ret
+    }
+
+    /// Every stone of color on the board, in row then column order.
+    pub fn get_stones(&self, color: Color) -> Vec<Intersection> {
+        let mut ret: Vec<Intersection> = vec![];
+        for r in 0..self.height {
+            for c in 0..self.width {
+                if let Some(&PointState::Filled { stone_color, .. }) = self.points.get(r as usize, c as usize) {
+                    if stone_color == color {
+                        ret.push(Intersection::new(r, c));
                     }
-                    _ => {}
                 }
             }
         }
+        ret
+    }
+
+    /// The number of liberties of the group on intersection, 0 for an empty point.
+    pub fn get_liberty_count(&self, intersection: Intersection) -> u32 {
+        self.group(intersection).1.len() as u32
+    }
+
+    // Takes a stone off the board without counting it as a prisoner, used
+    // when clearing dead stones for scoring.
+    pub(crate) fn remove_stone(&mut self, intersection: Intersection) {
+        if let Some(&PointState::Filled {
+            move_number: _,
+            stone_color,
+        }) = self.points.get(intersection.row as usize, intersection.col as usize)
+        {
+            self.points
+                .set(intersection.row as usize, intersection.col as usize, PointState::Empty)
+                .ok();
+            self.hash ^= zobrist_key(intersection, stone_color);
+        }
+    }
+
+    // Puts a stone on intersection, replacing any stone there, without
+    // capturing anything, as SGF setup properties do.
+    pub(crate) fn set_stone(&mut self, intersection: Intersection, stone_color: Color) -> Result<(), Error> {
+        if self.points.get(intersection.row as usize, intersection.col as usize).is_none() {
+            return Err(Error::InvalidMove("Point not found".to_string()));
+        }
+        self.remove_stone(intersection);
+        self.points
+            .set(
+                intersection.row as usize,
+                intersection.col as usize,
+                PointState::Filled {
+                    move_number: 0,
+                    stone_color,
+                },
+            )
+            .ok();
+        self.hash ^= zobrist_key(intersection, stone_color);
+        self.ko_point = None;
         Ok(())
     }
 
-    fn check_point(
-        &mut self,
-        group_assignments: &mut array2d::Array2D<GroupPoint>,
-        group_liberties: &mut HashSet<Intersection>,
-        group_members: &mut HashSet<Intersection>,
-        group_color: Color,
-        intersection: Intersection,
-        group_number: i32,
-    ) -> Result<bool, Error> {
-        match group_assignments.get(intersection.row as usize, intersection.col as usize) {
-            Some(&GroupPoint::Ungrouped) => {
-                match self.points.get(intersection.row as usize, intersection.col as usize) {
+    // The points orthogonally adjacent to intersection that are on the board.
+    pub(crate) fn neighbours(&self, intersection: Intersection) -> Vec<Intersection> {
+        let mut ret = Vec::with_capacity(4);
+        if intersection.row > 0 {
+            ret.push(intersection.down());
+        }
+        if intersection.row + 1 < self.height {
+            ret.push(intersection.up());
+        }
+        if intersection.col > 0 {
+            ret.push(intersection.left());
+        }
+        if intersection.col + 1 < self.width {
+            ret.push(intersection.right());
+        }
+        ret
+    }
+
+    // The stones connected to intersection and their liberties, found with an
+    // iterative flood fill. Both sets are empty if the point has no stone.
+    fn group(&self, intersection: Intersection) -> (HashSet<Intersection>, HashSet<Intersection>) {
+        let mut group_members: HashSet<Intersection> = HashSet::new();
+        let mut group_liberties: HashSet<Intersection> = HashSet::new();
+        let group_color = match self.points.get(intersection.row as usize, intersection.col as usize) {
+            Some(&PointState::Filled {
+                move_number: _,
+                stone_color,
+            }) => stone_color,
+            _ => return (group_members, group_liberties),
+        };
+        let mut pending = vec![intersection];
+        group_members.insert(intersection);
+        while let Some(member) = pending.pop() {
+            for neighbour in self.neighbours(member) {
+                match self.points.get(neighbour.row as usize, neighbour.col as usize) {
                     Some(&PointState::Empty) => {
-                        group_liberties.insert(intersection);
-                        Ok(false)
+                        group_liberties.insert(neighbour);
                     }
                     Some(&PointState::Filled {
                         move_number: _,
                         stone_color,
-                    }) => {
-                        if stone_color == group_color {
-                            group_assignments
-                                .set(
-                                    intersection.row as usize,
-                                    intersection.col as usize,
-                                    GroupPoint::Grouped { group_number },
-                                )
-                                .or(Err(Error::InvalidBoardNumber("Could not set group".to_string())))
-                                .ok();
-                            group_members.insert(intersection);
-                            if intersection.row > 0 {
-                                self.check_point(
-                                    group_assignments,
-                                    group_liberties,
-                                    group_members,
-                                    group_color,
-                                    intersection.down(),
-                                    group_number,
-                                )?;
-                            }
-                            if intersection.row < self.size {
-                                self.check_point(
-                                    group_assignments,
-                                    group_liberties,
-                                    group_members,
-                                    group_color,
-                                    intersection.up(),
-                                    group_number,
-                                )?;
-                            }
-                            if intersection.col > 0 {
-                                self.check_point(
-                                    group_assignments,
-                                    group_liberties,
-                                    group_members,
-                                    group_color,
-                                    intersection.left(),
-                                    group_number,
-                                )?;
-                            }
-                            if intersection.col < self.size {
-                                self.check_point(
-                                    group_assignments,
-                                    group_liberties,
-                                    group_members,
-                                    group_color,
-                                    intersection.right(),
-                                    group_number,
-                                )?;
-                            }
-                            Ok(true)
-                        } else {
-                            Ok(false)
-                        }
+                    }) if stone_color == group_color && !group_members.contains(&neighbour) => {
+                        group_members.insert(neighbour);
+                        pending.push(neighbour);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        (group_members, group_liberties)
+    }
+
+    // Only the groups touching the stone just placed can have lost their last
+    // liberty, so those are the only ones examined.
+    fn remove_captures(&mut self, intersection: Intersection, placed_stone_color: Color) -> Vec<Intersection> {
+        let mut captures: Vec<Intersection> = vec![];
+        for neighbour in self.neighbours(intersection) {
+            if let Some(&PointState::Filled {
+                move_number: _,
+                stone_color,
+            }) = self.points.get(neighbour.row as usize, neighbour.col as usize)
+            {
+                if stone_color == placed_stone_color {
+                    continue;
+                }
+                let (group_members, group_liberties) = self.group(neighbour);
+                if group_liberties.is_empty() {
+                    for member in group_members {
+                        self.points
+                            .set(member.row as usize, member.col as usize, PointState::Empty)
+                            .ok();
+                        self.hash ^= zobrist_key(member, stone_color);
+                        captures.push(member);
                     }
-                    None => Err(Error::InvalidBoardNumber("Invalid board".to_string())),
                 }
             }
-            _ => Ok(false),
         }
+        captures
     }
 
     pub fn to_ascii(&self) -> String {
+        self.to_ascii_with_markup(&Markup::default(), false)
+    }
+
+    /// Like to_ascii, with column letters above and row numbers to the left
+    /// of the board, labelled as in the LaTeX output.
+    pub fn to_ascii_with_coordinates(&self) -> String {
+        self.to_ascii_with_markup(&Markup::default(), true)
+    }
+
+    /// The ASCII board with markup drawn after the stone or point: "@" for
+    /// circles, "#" squares, "^" triangles, "x" crosses, "*" selected points,
+    /// "~" dimmed points and the first letter of labels. Arrows and lines are
+    /// listed below the board.
+    pub fn to_ascii_with_markup(&self, markup: &Markup, coordinates: bool) -> String {
         let mut ret: String = "".to_string();
+        if coordinates {
+            ret += "   ";
+            for c in 0..self.width {
+                ret += &format!("{:<3}", column_label(c));
+            }
+            ret = ret.trim_end().to_string();
+            ret += "\n";
+        }
 
-        for r in 0..self.size {
-            for c in 0..self.size {
+        for r in 0..self.height {
+            if coordinates {
+                ret += &format!("{:>2} ", r + 1);
+            }
+            for c in 0..self.width {
                 match self.points.get(r as usize, c as usize) {
                     Some(&PointState::Filled {
                         move_number: _,
                         stone_color,
                     }) => match stone_color {
                         Color::Black => {
-                            ret += &"X  ".to_string();
+                            ret += "X";
                         }
                         Color::White => {
-                            ret += &"O  ".to_string();
+                            ret += "O";
                         }
                     },
                     _ => {
-                        ret += &".  ".to_string();
+                        ret += ".";
                     }
                 }
+                ret.push(markup.ascii_symbol(Intersection::new(r, c)).unwrap_or(' '));
+                ret += " ";
             }
-            ret += &"\n".to_string();
-        }
-        return ret;
-    }
-
-    //    pub fn groups_to_ascii(&self, group_assignments: &array2d::Array2D<GroupPoint>) -> String {
-    //        let mut ret : String = "".to_string();
-    //        for c in 0..self.size {
-    //            for r in 0..self.size {
-    //                match group_assignments.get(r, c) {
-    //                    Some(&GroupPoint::Grouped { group_number }) => {
-    //                        ret += &format!("{:<3}", group_number);
-    //                    }
-    //                    _ => {
-    //                        ret += &".  ".to_string();
-    //                    }
-    //                }
-    //            }
-    //            ret += &"\n".to_string();
-    //        }
-    //        return ret;
-    //    }
+            ret += "\n";
+        }
+        for (kind, pairs) in [("arrow", markup.get_arrows()), ("line", markup.get_lines())] {
+            for (from, to) in pairs {
+                ret += &format!("{} {} -> {}\n", kind, from.to_coord(), to.to_coord());
+            }
+        }
+        ret
+    }
 }
@@ -1,117 +1,754 @@
 use sgf_parser::*;
+use std::fmt;
 use std::fs;
 
 use anyhow::{Result};
-use std::array::IntoIter;
 use std::collections::BTreeMap;
-use std::iter::FromIterator;
+use std::collections::BTreeSet;
 
 use super::Board;
+use super::Ruleset;
+use super::SuicideRule;
+use super::SuperkoRule;
 use super::Error;
+use super::color_to_string;
+use super::GameInfo;
+use super::game_info::escape_text;
 use super::Intersection;
+use super::Emphasis;
+use super::Markup;
+use super::Move;
+use super::MoveAnnotation;
+use super::PositionAnnotation;
+use super::Node;
+use super::PointState;
+use super::NodeId;
+use super::Score;
+use super::ScoringMethod;
+
+/// The outcome recorded in an SGF `RE` property.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameResult {
+    /// Won by the given number of points.
+    Points(Color, f32),
+    Resign(Color),
+    Time(Color),
+    Forfeit(Color),
+    /// Won with no reason or margin given, e.g. "B+".
+    Win(Color),
+    Draw,
+    /// No result or suspended play.
+    Void,
+    /// "?" or any value that does not follow the SGF format.
+    Unknown(String),
+}
+
+impl GameResult {
+    pub fn from_sgf(value: &str) -> GameResult {
+        let winner = match value.get(0..2) {
+            Some("B+") => Color::Black,
+            Some("W+") => Color::White,
+            _ => {
+                return match value {
+                    "0" | "D" | "Draw" | "Jigo" => GameResult::Draw,
+                    "Void" => GameResult::Void,
+                    _ => GameResult::Unknown(value.to_string()),
+                }
+            }
+        };
+        match &value[2..] {
+            "" => GameResult::Win(winner),
+            "R" | "Resign" => GameResult::Resign(winner),
+            "T" | "Time" => GameResult::Time(winner),
+            "F" | "Forfeit" => GameResult::Forfeit(winner),
+            points => match points.parse::<f32>() {
+                Ok(points) => GameResult::Points(winner, points),
+                Err(_) => GameResult::Unknown(value.to_string()),
+            },
+        }
+    }
+
+    fn from_outcome(outcome: &Outcome) -> GameResult {
+        match *outcome {
+            Outcome::WinnerByPoints(color, points) => GameResult::Points(color, points),
+            Outcome::WinnerByResign(color) => GameResult::Resign(color),
+            Outcome::WinnerByTime(color) => GameResult::Time(color),
+            Outcome::WinnerByForfeit(color) => GameResult::Forfeit(color),
+            Outcome::Draw => GameResult::Draw,
+        }
+    }
+
+    pub fn get_winner(&self) -> Option<Color> {
+        match self {
+            GameResult::Points(color, _)
+            | GameResult::Resign(color)
+            | GameResult::Time(color)
+            | GameResult::Forfeit(color)
+            | GameResult::Win(color) => Some(*color),
+            _ => None,
+        }
+    }
+
+    /// The winning margin of a game decided by counting.
+    pub fn get_margin(&self) -> Option<f32> {
+        match self {
+            GameResult::Points(_, points) => Some(*points),
+            GameResult::Draw => Some(0.0),
+            _ => None,
+        }
+    }
+
+    pub fn to_sgf_string(&self) -> String {
+        let winner = |color: &Color| match color {
+            Color::Black => "B+",
+            Color::White => "W+",
+        };
+        match self {
+            GameResult::Points(color, points) => format!("{}{}", winner(color), points),
+            GameResult::Resign(color) => format!("{}Resign", winner(color)),
+            GameResult::Time(color) => format!("{}Time", winner(color)),
+            GameResult::Forfeit(color) => format!("{}Forfeit", winner(color)),
+            GameResult::Win(color) => winner(color).to_string(),
+            GameResult::Draw => "Draw".to_string(),
+            GameResult::Void => "Void".to_string(),
+            GameResult::Unknown(value) => value.clone(),
+        }
+    }
+}
+
+fn color_to_sgf(color: Color) -> &'static str {
+    match color {
+        Color::Black => "B",
+        Color::White => "W",
+    }
+}
+
+// Text with the characters LaTeX treats specially escaped.
+pub(crate) fn latex_escape(text: &str) -> String {
+    let mut ret = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => ret += "\\textbackslash{}",
+            '~' => ret += "\\textasciitilde{}",
+            '^' => ret += "\\textasciicircum{}",
+            '#' | '$' | '%' | '&' | '_' | '{' | '}' => {
+                ret.push('\\');
+                ret.push(c);
+            }
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+// The SGF move property for color playing intersection, or passing for None.
+fn move_token(color: Color, intersection: Option<Intersection>) -> SgfToken {
+    let action = match intersection {
+        Some(intersection) => Action::Move(intersection.col() as u8 + 1, intersection.row() as u8 + 1),
+        None => Action::Pass,
+    };
+    SgfToken::Move { color, action }
+}
+
+// Whether token is a B or W move, readable or not. to_sgf writes each node's
+// move from what was played, leaving out moves that lenient loading skipped.
+fn is_move(token: &SgfToken) -> bool {
+    match token {
+        SgfToken::Move { .. } => true,
+        SgfToken::Invalid((ident, _)) => ident == "B" || ident == "W",
+        _ => false,
+    }
+}
+
+// Whether to_sgf writes token from the game's own fields and initial
+// position rather than from the root node's properties.
+fn is_game_field(token: &SgfToken) -> bool {
+    match token {
+        SgfToken::Unknown((ident, _)) | SgfToken::Invalid((ident, _)) => {
+            GameInfo::is_property(ident) || ["RE", "AB", "AW", "AE", "PL"].contains(&ident.as_str())
+        }
+        _ => matches!(
+            token,
+            SgfToken::Size(..)
+                | SgfToken::FileFormat(_)
+                | SgfToken::Game(_)
+                | SgfToken::PlayerName { .. }
+                | SgfToken::PlayerRank { .. }
+                | SgfToken::Event(_)
+                | SgfToken::Date(_)
+                | SgfToken::Place(_)
+                | SgfToken::GameName(_)
+                | SgfToken::Overtime(_)
+                | SgfToken::TimeLimit(_)
+                | SgfToken::Rule(_)
+                | SgfToken::Komi(_)
+                | SgfToken::Handicap(_)
+                | SgfToken::Result(_)
+                | SgfToken::Add { .. }
+        ),
+    }
+}
+
+// The points of an SGF point list value, either a single point "cd" or a
+// rectangle "aa:cc" given by two opposite corners.
+pub(crate) fn point_list(value: &str) -> Result<Vec<Intersection>, Error> {
+    let line = |c: u8| match c {
+        b'a'..=b'z' => Ok((c - b'a') as u32),
+        b'A'..=b'Z' => Ok((c - b'A') as u32 + 26),
+        _ => Err(Error::ParseError(format!("invalid point {}", value))),
+    };
+    let point = |p: &str| match p.as_bytes() {
+        [col, row] => Ok((line(*col)?, line(*row)?)),
+        _ => Err(Error::ParseError(format!("invalid point {}", value))),
+    };
+    let (from, to) = match value.split_once(':') {
+        Some((from, to)) => (point(from)?, point(to)?),
+        None => (point(value)?, point(value)?),
+    };
+    let mut ret = vec![];
+    for row in from.1.min(to.1)..=from.1.max(to.1) {
+        for col in from.0.min(to.0)..=from.0.max(to.0) {
+            ret.push(Intersection::new(row, col));
+        }
+    }
+    Ok(ret)
+}
+
+/// What Game does with a move or pass by the player who is not to play,
+/// such as two black moves in a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TurnCheck {
+    Allow,
+    /// Accept the move and record a warning, see `Game::get_warnings`.
+    #[default]
+    Warn,
+    /// Reject the move with `Error::OutOfTurn`.
+    Error,
+}
+
+/// What lenient loading does with a move that cannot be played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BadMove {
+    /// Leave the move out and carry on from the position before it.
+    Skip,
+    /// Play the move anyway, replacing any stone on the point and ignoring
+    /// ko, superko, suicide and turn order. Moves off the board are skipped.
+    ForcePlace,
+}
+
+/// A problem found while loading an SGF leniently.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    node_path: Vec<usize>,
+    move_number: u32,
+    reason: String,
+}
+
+impl Diagnostic {
+    /// The index of the variation taken at each branch from the root,
+    /// followed by the index of the node within its sequence.
+    pub fn get_node_path(&self) -> Vec<usize> {
+        self.node_path.clone()
+    }
+
+    /// The number the offending move had, or would have had, in its line.
+    pub fn get_move_number(&self) -> u32 {
+        self.move_number
+    }
+
+    pub fn get_reason(&self) -> String {
+        self.reason.clone()
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path: Vec<String> = self.node_path.iter().map(|i| i.to_string()).collect();
+        write!(f, "node {}, move {}: {}", path.join("."), self.move_number, self.reason)
+    }
+}
+
+// Lenient loading state threaded through add_moves.
+struct Lenient {
+    bad_move: BadMove,
+    diagnostics: Vec<Diagnostic>,
+}
 
 #[derive(Default, Debug, Clone)]
 pub struct Game {
-    board_size: u32,
-    player_black: String,
-    player_white: String,
-    rank_black: String,
-    rank_white: String,
-    board_positions: BTreeMap<u32, Board>,
-    board_number: u32,
+    board_width: u32,
+    board_height: u32,
+    info: GameInfo,
+    nodes: Vec<Node>,
+    board_positions: BTreeMap<NodeId, Board>,
+    ruleset: Ruleset,
+    // Whether the ruleset came from RU or a setter rather than the default.
+    ruleset_stated: bool,
+    komi: Option<f32>,
+    handicap: u32,
+    result: Option<GameResult>,
+    turn_check: TurnCheck,
+    warnings: Vec<String>,
 }
 
 impl Game {
     pub fn new(board_size: u32) -> Game {
+        Game::with_dimensions(board_size, board_size)
+    }
+
+    /// A game on a board width columns wide and height rows high.
+    pub fn with_dimensions(board_width: u32, board_height: u32) -> Game {
         Game {
-            board_size,
-            player_black: "".to_string(),
-            player_white: "".to_string(),
-            rank_black: "".to_string(),
-            rank_white: "".to_string(),
-            board_positions: BTreeMap::from_iter(IntoIter::new([(0, Board::new(board_size))])),
-            board_number: 0,
+            board_width,
+            board_height,
+            info: GameInfo::new(),
+            nodes: vec![Node::default()],
+            board_positions: BTreeMap::from([(0, Board::with_dimensions(board_width, board_height))]),
+            ruleset: Ruleset::default(),
+            ruleset_stated: false,
+            komi: None,
+            handicap: 0,
+            result: None,
+            turn_check: TurnCheck::default(),
+            warnings: vec![],
         }
     }
 
     pub fn from_game_tree(tree: &sgf_parser::GameTree) -> Result<Game, Error> {
-        let mut game = Game::default();
+        Game::from_game_tree_with_turn_check(tree, TurnCheck::default())
+    }
+
+    /// Like from_game_tree, handling moves played out of turn as turn_check says.
+    pub fn from_game_tree_with_turn_check(tree: &sgf_parser::GameTree, turn_check: TurnCheck) -> Result<Game, Error> {
+        Game::load(tree, turn_check, &mut None)
+    }
+
+    /// Loads as much of tree as possible, dealing with moves that cannot be
+    /// played as bad_move says and reporting each of them. Moves out of turn
+    /// are reported here too rather than in `get_warnings`.
+    pub fn from_game_tree_lenient(
+        tree: &sgf_parser::GameTree,
+        bad_move: BadMove,
+    ) -> Result<(Game, Vec<Diagnostic>), Error> {
+        let mut lenient = Some(Lenient { bad_move, diagnostics: vec![] });
+        let game = Game::load(tree, TurnCheck::default(), &mut lenient)?;
+        Ok((game, lenient.map(|l| l.diagnostics).unwrap_or_default()))
+    }
+
+    fn load(tree: &sgf_parser::GameTree, turn_check: TurnCheck, lenient: &mut Option<Lenient>) -> Result<Game, Error> {
+        // SGF boards are 19x19 unless SZ says otherwise.
+        let mut game = Game { turn_check, ..Game::new(19) };
 
         for game_node in &tree.nodes {
             for token in &game_node.tokens {
                 match &token {
-                    SgfToken::Size(size, _) => {
-                        game.board_size = *size;
-                        game.board_positions = BTreeMap::from_iter(IntoIter::new([(0, Board::new(game.board_size))]));
+                    SgfToken::Size(width, height) if *width > 52 || *height > 52 => {
+                        return Err(Error::ParseError(format!("board size {}x{} is larger than SGF allows", width, height)));
+                    },
+                    SgfToken::Size(width, height) => {
+                        game.board_width = *width;
+                        game.board_height = *height;
+                        game.board_positions = BTreeMap::from([(0, Board::with_dimensions(*width, *height))]);
                     },
                     SgfToken::PlayerName { color: sgf_parser::Color::Black, name } => {
-                        game.player_black = name.to_string();
+                        game.info.set_property("PB", name);
                     },
                     SgfToken::PlayerName { color: sgf_parser::Color::White, name } => {
-                        game.player_white = name.to_string();
+                        game.info.set_property("PW", name);
                     },
                     SgfToken::PlayerRank { color: sgf_parser::Color::Black, rank } => {
-                        game.rank_black = rank.to_string();
+                        game.info.set_property("BR", rank);
                     },
                     SgfToken::PlayerRank { color: sgf_parser::Color::White, rank } => {
-                        game.rank_white = rank.to_string();
+                        game.info.set_property("WR", rank);
+                    },
+                    SgfToken::Event(event) => {
+                        game.info.set_property("EV", event);
+                    },
+                    SgfToken::Date(date) => {
+                        game.info.set_property("DT", date);
+                    },
+                    SgfToken::Place(place) => {
+                        game.info.set_property("PC", place);
+                    },
+                    SgfToken::GameName(name) => {
+                        game.info.set_property("GN", name);
+                    },
+                    SgfToken::Overtime(overtime) => {
+                        game.info.set_property("OT", overtime);
+                    },
+                    SgfToken::TimeLimit(time) => {
+                        game.info.set_property("TM", &time.to_string());
+                    },
+                    // RO, SO, US, CP, AN, ON, GC, BT and WT are not known to
+                    // sgf_parser, and it rejects a TM that is not a whole number.
+                    SgfToken::Unknown((ident, value)) | SgfToken::Invalid((ident, value))
+                        if game.info.set_property(ident, value) => {},
+                    SgfToken::Rule(rule) => {
+                        game.ruleset = Ruleset::from_sgf(rule);
+                        game.ruleset_stated = true;
+                    },
+                    SgfToken::Komi(komi) => {
+                        game.komi = Some(*komi);
+                    },
+                    SgfToken::Handicap(handicap) => {
+                        game.handicap = *handicap;
+                    },
+                    SgfToken::Result(outcome) => {
+                        game.result = Some(GameResult::from_outcome(outcome));
+                    },
+                    // sgf_parser rejects results such as "Void", "?" and "B+".
+                    SgfToken::Invalid((ident, value)) if ident == "RE" => {
+                        game.result = Some(GameResult::from_sgf(value));
                     },
                     _ => {}
                 }
             }
         }
 
-        game.add_moves(tree, 0)?;
+        // White moves first after handicap stones unless PL says otherwise.
+        if game.handicap >= 2 {
+            if let Some(board) = game.board_positions.get_mut(&0) {
+                board.set_to_play(Some(Color::White));
+            }
+        }
+        // Every later position starts from a copy of board 0.
+        game.update_board_rulesets();
+        game.add_moves(tree, 0, &[], lenient)?;
         Ok(game)
     }
 
-    fn add_moves(&mut self, tree: &sgf_parser::GameTree, board_number: u32 ) -> Result<(), Error> {
+    // Adds a node for each node of tree, the first a child of node. path
+    // holds the variations taken to reach tree, and is empty for the root
+    // sequence.
+    fn add_moves(
+        &mut self,
+        tree: &sgf_parser::GameTree,
+        node: NodeId,
+        path: &[usize],
+        lenient: &mut Option<Lenient>,
+    ) -> Result<(), Error> {
 
-        let mut bn = board_number;
+        let mut bn = node;
 
-        for game_node in &tree.nodes {
+        for (i, game_node) in tree.nodes.iter().enumerate() {
+            let node_path = [path, &[i]].concat();
+            let is_root = path.is_empty() && i == 0;
+            let mut board = self
+                .board_positions
+                .get(&bn)
+                .ok_or_else(|| Error::InvalidBoardNumber("No previous board position".to_string()))?
+                .clone();
+            // A node whose setup fails keeps the position before it.
+            let mut setup_board = board.clone();
+            match (Game::apply_setup(&mut setup_board, game_node), lenient.as_mut()) {
+                (Ok(found), _) => {
+                    // Setup on the root is the starting position, not an edit.
+                    setup_board.set_setup(found && !is_root);
+                    board = setup_board;
+                }
+                (Err(e), None) => return Err(e),
+                (Err(e), Some(lenient)) => lenient.diagnostics.push(Diagnostic {
+                    node_path: node_path.clone(),
+                    move_number: board.get_move_number(),
+                    reason: format!("{}; setup skipped", e),
+                }),
+            }
+            let mut moves = vec![];
             for token in &game_node.tokens {
                 match &token {
-                    SgfToken::Add { color: sgf_parser::Color::Black, coordinate } => {
-                        self.place_handicap_stone(Intersection::from_sgf(coordinate.1.into(), coordinate.0.into()))?;
+                    // FF[3] and earlier wrote a pass as "tt" on boards up to 19x19.
+                    SgfToken::Move { color, action: Action::Move(20, 20) } if self.board_width <= 19 && self.board_height <= 19 => {
+                        moves.push((*color, None));
                     },
                     SgfToken::Move { color, action: Action::Move(col, row) } => {
-                        self.place_stone(Intersection::from_sgf(*row as u32, *col as u32), *color, bn)?;
-                        bn = self.board_number;
+                        moves.push((*color, Some(Intersection::from_sgf(*row as u32, *col as u32))));
                     },
-                    _ => {}
+                    SgfToken::Move { color, action: Action::Pass } => moves.push((*color, None)),
+                    // Strict loading has always ignored moves sgf_parser could not read.
+                    SgfToken::Invalid((ident, value)) if ident == "B" || ident == "W" => {
+                        if let Some(lenient) = lenient.as_mut() {
+                            lenient.diagnostics.push(Diagnostic {
+                                node_path: node_path.clone(),
+                                move_number: board.get_move_number() + 1,
+                                reason: format!("unreadable move {}[{}]; skipped", ident, value),
+                            });
+                        }
+                    },
+                    _ => {},
+                }
+            }
+            let mut mv = None;
+            if let Some(&(color, intersection)) = moves.first() {
+                if let Some(new_board) = self.play_leniently(&board, intersection, color, bn, &node_path, lenient)? {
+                    mv = new_board.get_last_move();
+                    board = new_board;
+                }
+            }
+            if is_root {
+                // The root node holds the initial position.
+                self.nodes[0] = Node::new(game_node.tokens.clone(), mv, None);
+                self.board_positions.insert(0, board);
+            } else {
+                bn = self.add_node(bn, game_node.tokens.clone(), mv, board);
+            }
+            // Any further moves in the node follow in nodes of their own.
+            for &(color, intersection) in moves.iter().skip(1) {
+                let board = self.board_positions[&bn].clone();
+                if let Some(new_board) = self.play_leniently(&board, intersection, color, bn, &node_path, lenient)? {
+                    let mv = new_board.get_last_move();
+                    bn = self.add_node(bn, vec![move_token(color, intersection)], mv, new_board);
                 }
             }
         }
-        let bn = self.board_number;
-        for v in &tree.variations {
-            self.add_moves(&v, bn)?;
+        for (i, v) in tree.variations.iter().enumerate() {
+            self.add_moves(v, bn, &[path, &[i]].concat(), lenient)?;
         }
         Ok(())
     }
 
+    // Plays a move read from the node at node_path. Strict loading fails on
+    // a move that cannot be played, lenient loading reports it and returns
+    // the forced position, or None when the move is skipped.
+    fn play_leniently(
+        &mut self,
+        board: &Board,
+        intersection: Option<Intersection>,
+        color: Color,
+        bn: NodeId,
+        node_path: &[usize],
+        lenient: &mut Option<Lenient>,
+    ) -> Result<Option<Board>, Error> {
+        let warnings = self.warnings.len();
+        let played = self.play(board, intersection, color, bn);
+        if let Some(lenient) = lenient.as_mut() {
+            // Moves out of turn are reported with everything else.
+            for reason in self.warnings.split_off(warnings) {
+                lenient.diagnostics.push(Diagnostic {
+                    node_path: node_path.to_vec(),
+                    move_number: board.get_move_number() + 1,
+                    reason,
+                });
+            }
+        }
+        match (played, lenient.as_mut()) {
+            (Ok(new_board), _) => Ok(Some(new_board)),
+            (Err(e), None) => Err(e),
+            (Err(e), Some(lenient)) => {
+                let forced = match (lenient.bad_move, intersection) {
+                    (BadMove::ForcePlace, Some(intersection)) => self.force_play(board, intersection, color).ok(),
+                    _ => None,
+                };
+                let outcome = match (&forced, intersection) {
+                    (None, _) => "skipped",
+                    // A suicidal stone is placed and then taken off again.
+                    (Some(new_board), Some(intersection))
+                        if matches!(new_board.get_point(intersection.row(), intersection.col()), Ok(PointState::Empty)) =>
+                    {
+                        "placed anyway and removed as suicide"
+                    }
+                    (Some(_), _) => "placed anyway",
+                };
+                lenient.diagnostics.push(Diagnostic {
+                    node_path: node_path.to_vec(),
+                    move_number: board.get_move_number() + 1,
+                    reason: format!("{}; {}", e, outcome),
+                });
+                Ok(forced)
+            }
+        }
+    }
+
+    // Applies the AB, AW, AE and PL properties of game_node to board, returning
+    // whether it had any. Setup properties in a node act together, so points
+    // are cleared before any stones are added.
+    fn apply_setup(board: &mut Board, game_node: &GameNode) -> Result<bool, Error> {
+        let mut found = false;
+        for token in &game_node.tokens {
+            if let SgfToken::Unknown((ident, value)) = &token {
+                if ident == "AE" {
+                    for intersection in point_list(value)? {
+                        board.remove_stone(intersection);
+                    }
+                    found = true;
+                }
+            }
+        }
+        for token in &game_node.tokens {
+            match &token {
+                SgfToken::Unknown((ident, value)) if ident == "PL" => {
+                    board.set_to_play(match value.as_str() {
+                        "B" | "b" => Some(Color::Black),
+                        "W" | "w" => Some(Color::White),
+                        _ => return Err(Error::ParseError(format!("invalid player {}", value))),
+                    });
+                    found = true;
+                },
+                _ => {}
+            }
+        }
+        for token in &game_node.tokens {
+            let (color, points) = match &token {
+                SgfToken::Add { color, coordinate } => {
+                    (*color, vec![Intersection::from_sgf(coordinate.1.into(), coordinate.0.into())])
+                },
+                // Compressed point lists such as AB[aa:cc] are rejected by sgf_parser.
+                SgfToken::Invalid((ident, value)) if ident == "AB" || ident == "AW" => {
+                    (if ident == "AB" { Color::Black } else { Color::White }, point_list(value)?)
+                },
+                _ => continue,
+            };
+            // Setup stones never capture.
+            for intersection in points {
+                board.set_stone(intersection, color)?;
+            }
+            found = true;
+        }
+        Ok(found)
+    }
+
+    /// The board width, which is also its height unless the board is rectangular.
     pub fn get_board_size(&self) -> u32 {
-        self.board_size
+        self.board_width
     }
 
+    pub fn get_board_width(&self) -> u32 {
+        self.board_width
+    }
+
+    pub fn get_board_height(&self) -> u32 {
+        self.board_height
+    }
+
+    /// The number of moves, passes included, on the main line.
     pub fn get_final_move_number(&self) -> u32 {
-        self.board_number
+        self.board_positions[&self.get_main_line_end()].get_move_number()
+    }
+
+    /// The highest node id. Nodes are numbered from 0 in the order they were
+    /// added, so every id up to this one is a node of the game.
+    pub fn get_last_node(&self) -> NodeId {
+        self.nodes.len() as NodeId - 1
+    }
+
+    pub fn get_node(&self, node: NodeId) -> Result<Node, Error> {
+        self.nodes
+            .get(node as usize)
+            .cloned()
+            .ok_or_else(|| Error::InvalidBoardNumber(node.to_string()))
     }
 
     pub fn get_player_black(&self) -> String {
-        self.player_black.clone()
+        self.info.get_player_black()
     }
 
     pub fn get_player_white(&self) -> String {
-        self.player_white.clone()
+        self.info.get_player_white()
     }
 
     pub fn get_rank_black(&self) -> String {
-        self.rank_black.clone()
+        self.info.get_rank_black()
     }
 
     pub fn get_rank_white(&self) -> String {
-        self.rank_white.clone()
+        self.info.get_rank_white()
+    }
+
+    pub fn get_info(&self) -> GameInfo {
+        self.info.clone()
+    }
+
+    pub fn set_info(&mut self, info: GameInfo) {
+        self.info = info;
+    }
+
+    /// Komi from the record's `KM` property.
+    pub fn get_komi(&self) -> Option<f32> {
+        self.komi
+    }
+
+    pub fn set_komi(&mut self, komi: Option<f32>) {
+        self.komi = komi;
+    }
+
+    /// Number of handicap stones from the record's `HA` property, 0 for an even game.
+    pub fn get_handicap(&self) -> u32 {
+        self.handicap
+    }
+
+    pub fn set_handicap(&mut self, handicap: u32) {
+        self.handicap = handicap;
+    }
+
+    pub fn get_result(&self) -> Option<GameResult> {
+        self.result.clone()
+    }
+
+    pub fn set_result(&mut self, result: Option<GameResult>) {
+        self.result = result;
+    }
+
+    pub fn get_turn_check(&self) -> TurnCheck {
+        self.turn_check
+    }
+
+    pub fn set_turn_check(&mut self, turn_check: TurnCheck) {
+        self.turn_check = turn_check;
+    }
+
+    /// Problems found while loading or playing that did not stop the game,
+    /// such as moves played out of turn.
+    pub fn get_warnings(&self) -> Vec<String> {
+        self.warnings.clone()
+    }
+
+    /// The player to move at board_number.
+    pub fn to_play(&self, board_number: u32) -> Result<Color, Error> {
+        self.board_positions
+            .get(&board_number)
+            .map(|board| board.to_play())
+            .ok_or_else(|| Error::InvalidBoardNumber(board_number.to_string()))
+    }
+
+    pub fn get_ruleset(&self) -> Ruleset {
+        self.ruleset.clone()
+    }
+
+    /// Overrides the ruleset read from the record's `RU` property.
+    pub fn set_ruleset(&mut self, ruleset: Ruleset) {
+        self.ruleset = ruleset;
+        self.ruleset_stated = true;
+        self.update_board_rulesets();
+    }
+
+    pub fn get_superko_rule(&self) -> SuperkoRule {
+        self.ruleset.get_superko_rule()
+    }
+
+    /// Switches to a custom ruleset that differs from the current one only in its superko rule.
+    pub fn set_superko_rule(&mut self, superko_rule: SuperkoRule) {
+        self.ruleset = self.ruleset.with_superko_rule(superko_rule);
+        self.ruleset_stated = true;
+        self.update_board_rulesets();
+    }
+
+    pub fn get_suicide_rule(&self) -> SuicideRule {
+        self.ruleset.get_suicide_rule()
+    }
+
+    /// Switches to a custom ruleset that differs from the current one only in its suicide rule.
+    pub fn set_suicide_rule(&mut self, suicide_rule: SuicideRule) {
+        self.ruleset = self.ruleset.with_suicide_rule(suicide_rule);
+        self.ruleset_stated = true;
+        self.update_board_rulesets();
+    }
+
+    // Boards check suicide against their own copy of the ruleset, so it is
+    // kept in step with the game's.
+    fn update_board_rulesets(&mut self) {
+        for board in self.board_positions.values_mut() {
+            board.set_ruleset(self.ruleset.clone());
+        }
     }
 
     pub fn get_board(&self, board_number: u32) -> Result<Board, Error> {
@@ -125,25 +762,257 @@ impl Game {
         }
     }
 
-    pub fn place_stone(&mut self, intersection: Intersection, color: Color, board_number : u32) -> Result<(), Error> {
-        if let Some(board) = self.board_positions.get_mut(&board_number) {
-            self.board_number += 1;
-            let mut new_board = board.clone();
-            new_board.clear_next();
-            board.add_next(self.board_number);
-            new_board.set_prev(board_number);
-            new_board.place_stone(
-                intersection,
-                color,
-            )?;
-            self.board_positions.insert(self.board_number, new_board);
-//            self.moves.insert(self.board_number, Move{move_number: self.board_number, intersection, color});
-            Ok(())
-        } else {
-            Err(Error::InvalidBoardNumber(
-                "No previous board position".to_string(),
-            ))
+    /// The nodes from the root down to node, both included.
+    pub fn get_line(&self, node: NodeId) -> Result<Vec<NodeId>, Error> {
+        let mut ret = vec![node];
+        let mut current = self.get_node(node)?;
+        while let Some(parent) = current.get_parent() {
+            ret.push(parent);
+            current = self.get_node(parent)?;
+        }
+        ret.reverse();
+        Ok(ret)
+    }
+
+    pub fn get_comment(&self, node: NodeId) -> Result<Option<String>, Error> {
+        Ok(self.get_node(node)?.get_comment())
+    }
+
+    pub fn get_name(&self, node: NodeId) -> Result<Option<String>, Error> {
+        Ok(self.get_node(node)?.get_name())
+    }
+
+    /// The markup shown at a node. Dimmed points (DD) stay in force until
+    /// a later node on the line sets them again, DD[] clearing them.
+    pub fn get_markup(&self, node: NodeId) -> Result<Markup, Error> {
+        let mut markup = self.get_node(node)?.get_markup();
+        for id in self.get_line(node)?.into_iter().rev() {
+            let properties = self.nodes[id as usize].get_properties();
+            if properties.iter().any(|token| matches!(token, SgfToken::Unknown((ident, _)) if ident == "DD")) {
+                markup.set_dimmed(Markup::from_properties(&properties).get_dimmed());
+                break;
+            }
+        }
+        Ok(markup)
+    }
+
+    /// The seconds each move on the line to `node` took, from the BL and
+    /// WL time left before and after it. The first move of each player is
+    /// measured from the TM main time. Moves without time left recorded,
+    /// or after which the clock went up, as when an overtime period
+    /// starts, are left out.
+    pub fn get_time_spent(&self, node: NodeId) -> Result<Vec<(NodeId, Color, f32)>, Error> {
+        let main_time = self.info.get_time_limit().trim().parse::<f32>().ok();
+        let (mut black_left, mut white_left) = (main_time, main_time);
+        let mut ret = vec![];
+        for id in self.get_line(node)? {
+            let node = &self.nodes[id as usize];
+            if let Some(mv) = node.get_move() {
+                let color = mv.get_color();
+                let before = match color {
+                    Color::Black => black_left,
+                    Color::White => white_left,
+                };
+                if let (Some(before), Some(after)) = (before, node.get_time_left(color)) {
+                    if after <= before {
+                        ret.push((id, color, before - after));
+                    }
+                }
+            }
+            black_left = node.get_time_left(Color::Black).or(black_left);
+            white_left = node.get_time_left(Color::White).or(white_left);
+        }
+        Ok(ret)
+    }
+
+    /// Every node whose move is annotated, in node order.
+    pub fn get_move_annotations(&self) -> Vec<(NodeId, MoveAnnotation)> {
+        self.find_nodes(|node| node.get_move_annotation())
+    }
+
+    /// Every node whose position is annotated, in node order.
+    pub fn get_position_annotations(&self) -> Vec<(NodeId, PositionAnnotation)> {
+        self.find_nodes(|node| node.get_position_annotation())
+    }
+
+    /// Every node marked as a hotspot, in node order.
+    pub fn get_hotspots(&self) -> Vec<(NodeId, Emphasis)> {
+        self.find_nodes(|node| node.get_hotspot())
+    }
+
+    fn find_nodes<T>(&self, f: impl Fn(&Node) -> Option<T>) -> Vec<(NodeId, T)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, node)| f(node).map(|value| (i as NodeId, value)))
+            .collect()
+    }
+
+    /// The last position of the main line, following the first variation at every branch.
+    pub fn get_main_line_end(&self) -> NodeId {
+        let mut bn = 0;
+        while let Some(next) = self.nodes.get(bn as usize).and_then(|node| node.get_child(0)) {
+            bn = next;
+        }
+        bn
+    }
+
+    /// Score of board_number under the game's ruleset, using the recorded
+    /// komi or the ruleset's default when there is none. dead_stones is
+    /// ignored under Tromp-Taylor rules, see `Ruleset::removes_dead_stones`.
+    pub fn score(&self, board_number: u32, dead_stones: &BTreeSet<Intersection>) -> Result<Score, Error> {
+        let board = self
+            .board_positions
+            .get(&board_number)
+            .ok_or_else(|| Error::InvalidBoardNumber(board_number.to_string()))?;
+        let komi = self.komi.unwrap_or_else(|| self.ruleset.get_default_komi());
+        let no_dead_stones = BTreeSet::new();
+        let dead_stones = if self.ruleset.removes_dead_stones() { dead_stones } else { &no_dead_stones };
+        Ok(match self.ruleset.get_scoring_method() {
+            ScoringMethod::Territory => board.score_territory(dead_stones, komi),
+            ScoringMethod::Area => board.score_area(dead_stones, komi),
+        })
+    }
+
+    /// Territory score of board_number with dead_stones taken off the board.
+    pub fn score_territory(
+        &self,
+        board_number: u32,
+        dead_stones: &BTreeSet<Intersection>,
+        komi: f32,
+    ) -> Result<Score, Error> {
+        let board = self
+            .board_positions
+            .get(&board_number)
+            .ok_or_else(|| Error::InvalidBoardNumber(board_number.to_string()))?;
+        Ok(board.score_territory(dead_stones, komi))
+    }
+
+    /// Area score of board_number with dead_stones taken off the board. Pass
+    /// an empty set for Tromp-Taylor scoring.
+    pub fn score_area(
+        &self,
+        board_number: u32,
+        dead_stones: &BTreeSet<Intersection>,
+        komi: f32,
+    ) -> Result<Score, Error> {
+        let board = self
+            .board_positions
+            .get(&board_number)
+            .ok_or_else(|| Error::InvalidBoardNumber(board_number.to_string()))?;
+        Ok(board.score_area(dead_stones, komi))
+    }
+
+    /// Plays color on intersection in a new node after board_number.
+    pub fn place_stone(&mut self, intersection: Intersection, color: Color, board_number : u32) -> Result<Vec<Intersection>, Error> {
+        let board = self
+            .board_positions
+            .get(&board_number)
+            .ok_or_else(|| Error::InvalidBoardNumber("No previous board position".to_string()))?
+            .clone();
+        let new_board = self.play(&board, Some(intersection), color, board_number)?;
+        let mv = new_board.get_last_move();
+        let captures = mv.as_ref().map(Move::get_captures).unwrap_or_default();
+        self.add_node(board_number, vec![move_token(color, Some(intersection))], mv, new_board);
+        Ok(captures)
+    }
+
+    /// Adds a pass by color in a new node after board_number. Passing never
+    /// violates superko.
+    pub fn pass(&mut self, color: Color, board_number: u32) -> Result<(), Error> {
+        let board = self
+            .board_positions
+            .get(&board_number)
+            .ok_or_else(|| Error::InvalidBoardNumber("No previous board position".to_string()))?
+            .clone();
+        let new_board = self.play(&board, None, color, board_number)?;
+        let mv = new_board.get_last_move();
+        self.add_node(board_number, vec![move_token(color, None)], mv, new_board);
+        Ok(())
+    }
+
+    // The position after color plays intersection, or passes for None, on
+    // board. node is where board sits in the tree, for superko.
+    fn play(&mut self, board: &Board, intersection: Option<Intersection>, color: Color, node: NodeId) -> Result<Board, Error> {
+        self.check_turn(board, color)?;
+        let mut new_board = board.clone();
+        match intersection {
+            Some(intersection) => {
+                new_board.place_stone(intersection, color)?;
+                // A node that edits the position starts a new line of play.
+                if !board.is_setup() && self.repeats_position(&new_board, node) {
+                    return Err(Error::SuperkoViolation(intersection.to_coord()));
+                }
+            }
+            None => new_board.pass(color),
+        }
+        Ok(new_board)
+    }
+
+    // The position after color plays intersection on board whatever the rules
+    // say, replacing any stone already there. Fails only if the point is off
+    // the board.
+    fn force_play(&self, board: &Board, intersection: Intersection, color: Color) -> Result<Board, Error> {
+        let mut new_board = board.clone();
+        new_board.get_point(intersection.row(), intersection.col())?;
+        new_board.remove_stone(intersection);
+        new_board.clear_ko_point();
+        new_board.set_ruleset(self.ruleset.with_suicide_rule(SuicideRule::Allowed));
+        new_board.place_stone(intersection, color)?;
+        new_board.set_ruleset(self.ruleset.clone());
+        Ok(new_board)
+    }
+
+    // Applies the turn check to color playing next on board.
+    fn check_turn(&mut self, board: &Board, color: Color) -> Result<(), Error> {
+        if color == board.to_play() || self.turn_check == TurnCheck::Allow {
+            return Ok(());
+        }
+        let message = format!(
+            "{} played move {} but {} was to play",
+            color_to_string(&color),
+            board.get_move_number() + 1,
+            color_to_string(&board.to_play())
+        );
+        match self.turn_check {
+            TurnCheck::Error => Err(Error::OutOfTurn(message)),
+            _ => {
+                self.warnings.push(message);
+                Ok(())
+            }
+        }
+    }
+
+    // Adds a child of parent with the position board reached there.
+    fn add_node(&mut self, parent: NodeId, properties: Vec<SgfToken>, mv: Option<Move>, board: Board) -> NodeId {
+        let node = self.nodes.len() as NodeId;
+        self.nodes.push(Node::new(properties, mv, Some(parent)));
+        self.nodes[parent as usize].add_child(node);
+        self.board_positions.insert(node, board);
+        node
+    }
+
+    // Walks the line of play back from node looking for a position that
+    // new_board repeats under the current superko rule.
+    fn repeats_position(&self, new_board: &Board, node: NodeId) -> bool {
+        let superko_rule = self.ruleset.get_superko_rule();
+        if superko_rule == SuperkoRule::None {
+            return false;
         }
+        let mut bn = node;
+        while let Some(board) = self.board_positions.get(&bn) {
+            if board.get_hash() == new_board.get_hash()
+                && (superko_rule == SuperkoRule::Positional || board.to_play() == new_board.to_play())
+            {
+                return true;
+            }
+            // Positions before an edit are not part of this line of play.
+            match self.nodes[bn as usize].get_parent() {
+                Some(parent) if !board.is_setup() => bn = parent,
+                _ => break,
+            }
+        }
+        false
     }
 
     pub fn place_handicap_stone(&mut self, intersection: Intersection) -> Result<(), Error> {
@@ -156,38 +1025,230 @@ impl Game {
         }
     }
 
+    /// Places 2 to 9 handicap stones on the standard star points of a 9x9,
+    /// 13x13 or 19x19 board, in the order the corners, sides and centre are
+    /// filled, and records the handicap.
+    pub fn place_handicap(&mut self, stones: u32) -> Result<Vec<Intersection>, Error> {
+        let edge = match (self.board_width, self.board_height) {
+            (9, 9) => 2,
+            (13, 13) | (19, 19) => 3,
+            (width, height) => {
+                return Err(Error::InvalidHandicap(format!("no fixed handicap on a {}x{} board", width, height)))
+            }
+        };
+        if !(2..=9).contains(&stones) {
+            return Err(Error::InvalidHandicap(format!("{} stones", stones)));
+        }
+        let far = self.board_width - 1 - edge;
+        let centre = self.board_width / 2;
+        let point = |row, col| Intersection::new(row, col);
+        let corners = [point(edge, far), point(far, edge), point(far, far), point(edge, edge)];
+        let sides = [point(centre, edge), point(centre, far), point(edge, centre), point(far, centre)];
+        let mut ret: Vec<Intersection> = corners.iter().take(stones.min(4) as usize).cloned().collect();
+        if stones >= 6 {
+            ret.extend(sides.iter().take(if stones >= 8 { 4 } else { 2 }));
+        }
+        if stones % 2 == 1 && stones >= 5 {
+            ret.push(point(centre, centre));
+        }
+        // Check every point first so that a failure leaves the board as it was.
+        let board = self.get_board(0)?;
+        for &intersection in &ret {
+            if !matches!(board.get_point(intersection.row(), intersection.col())?, PointState::Empty) {
+                return Err(Error::InvalidMove(format!("{} is already filled", intersection.to_coord())));
+            }
+        }
+        for &intersection in &ret {
+            self.place_handicap_stone(intersection)?;
+        }
+        self.handicap = stones;
+        if let Some(board) = self.board_positions.get_mut(&0) {
+            board.set_to_play(Some(Color::White));
+        }
+        Ok(ret)
+    }
+
+    /// Adds a setup stone of color to the initial position, replacing any
+    /// stone already there. Like AB and AW it captures nothing.
+    pub fn add_setup_stone(&mut self, intersection: Intersection, color: Color) -> Result<(), Error> {
+        if let Some(board) = self.board_positions.get_mut(&0) {
+            board.set_stone(intersection, color)
+        } else {
+            Err(Error::InvalidBoardNumber("Invalid board".to_string()))
+        }
+    }
+
+    /// Removes any stone on intersection from the initial position.
+    pub fn clear_setup_point(&mut self, intersection: Intersection) {
+        if let Some(board) = self.board_positions.get_mut(&0) {
+            board.remove_stone(intersection);
+        }
+    }
+
     pub fn from_sgf_file(file_name: String) -> Result<Game, Error> {
         let sgf_source = fs::read_to_string(file_name)?;
         let tree= parse(sgf_source.as_str())?;
         Game::from_game_tree(&tree)
     }
 
+    /// Like from_sgf_file, loading leniently as from_game_tree_lenient does.
+    pub fn from_sgf_file_lenient(file_name: String, bad_move: BadMove) -> Result<(Game, Vec<Diagnostic>), Error> {
+        let sgf_source = fs::read_to_string(file_name)?;
+        let tree = parse(sgf_source.as_str())?;
+        Game::from_game_tree_lenient(&tree, bad_move)
+    }
+
+    /// The game as an SGF record: game info and setup on the root node,
+    /// followed by every line of play.
+    pub fn to_sgf(&self) -> String {
+        let mut ret = if self.board_width == self.board_height {
+            format!("(;FF[4]GM[1]SZ[{}]", self.board_width)
+        } else {
+            format!("(;FF[4]GM[1]SZ[{}:{}]", self.board_width, self.board_height)
+        };
+        ret += &self.info.to_sgf();
+        if self.ruleset_stated && !self.ruleset.get_name().is_empty() {
+            ret += &format!("RU[{}]", escape_text(&self.ruleset.get_name()));
+        }
+        if let Some(komi) = self.komi {
+            ret += &format!("KM[{}]", komi);
+        }
+        if self.handicap > 0 {
+            ret += &format!("HA[{}]", self.handicap);
+        }
+        if let Some(result) = &self.result {
+            ret += &format!("RE[{}]", escape_text(&result.to_sgf_string()));
+        }
+        if let Some(board) = self.board_positions.get(&0) {
+            for color in [Color::Black, Color::White] {
+                // Leaving out a stone played by a move in the root node.
+                let stones: String = board
+                    .get_stones(color)
+                    .iter()
+                    .filter(|intersection| {
+                        matches!(
+                            board.get_point(intersection.row(), intersection.col()),
+                            Ok(PointState::Filled { move_number: 0, .. })
+                        )
+                    })
+                    .map(|intersection| format!("[{}]", intersection.to_sgf()))
+                    .collect();
+                if !stones.is_empty() {
+                    ret += &format!("A{}{}", color_to_sgf(color), stones);
+                }
+            }
+            if let Some(color) = board.explicit_to_play() {
+                ret += &format!("PL[{}]", color_to_sgf(color));
+            }
+        }
+        ret += &self.node_to_sgf(0);
+        self.write_nodes(&mut ret, 0);
+        ret += ")\n";
+        ret
+    }
+
+    pub fn to_sgf_file(&self, file_name: String) -> Result<(), Error> {
+        fs::write(file_name, self.to_sgf())?;
+        Ok(())
+    }
+
+    // The move played at node followed by its other properties, less those
+    // the root's are written from.
+    fn node_to_sgf(&self, node: NodeId) -> String {
+        let node_data = &self.nodes[node as usize];
+        let mut ret: String = match node_data.get_move() {
+            Some(mv) => (&move_token(mv.get_color(), mv.get_intersection())).into(),
+            None => String::new(),
+        };
+        // sgf_parser gives a token per value, as in CR[aa] CR[bb] for
+        // CR[aa][bb], but a property may only appear once in a node.
+        let mut last_ident = String::new();
+        for token in node_data.get_properties() {
+            if !is_move(&token) && (node != 0 || !is_game_field(&token)) {
+                let property: String = (&token).into();
+                let (ident, values) = property.split_at(property.find('[').unwrap_or(0));
+                if ident != last_ident {
+                    ret += ident;
+                    last_ident = ident.to_string();
+                }
+                ret += values;
+            }
+        }
+        ret
+    }
+
+    // Appends the nodes below node, bracketing each variation where the line
+    // of play branches.
+    fn write_nodes(&self, sgf: &mut String, node: NodeId) {
+        let children = self.nodes[node as usize].get_children();
+        for &child in &children {
+            if children.len() > 1 {
+                *sgf += "(";
+            }
+            *sgf += ";";
+            *sgf += &self.node_to_sgf(child);
+            self.write_nodes(sgf, child);
+            if children.len() > 1 {
+                *sgf += ")";
+            }
+        }
+    }
+
+    /// Diagrams of the main line, each showing step_size more moves.
     pub fn render_to_latex(&self, step_size: u32) -> Result<String, Error> {
-        let mut move_number = 0;
+        let mut to = 0;
         let mut ret: String = "".to_string();
-        while move_number < self.board_number {
-            ret += &self.render_board_to_latex(
-                if move_number + step_size < self.board_number {
-                    move_number + step_size
-                } else {
-                    self.board_number
-                },
-                Some(move_number),
-            )?;
-            move_number += step_size;
+        let last = self.get_final_move_number();
+        let main_line = self.get_line(self.get_main_line_end())?;
+        while to < last {
+            // Each diagram starts after the last move of the one before.
+            let from = if to == 0 { 0 } else { to + 1 };
+            to = (to + step_size).min(last);
+            // The last node up to that move, so that trailing comments are included.
+            let node = main_line
+                .iter()
+                .copied()
+                .rev()
+                .find(|node| self.board_positions[node].get_move_number() <= to)
+                .unwrap_or(0);
+            ret += &self.render_board_to_latex(node, Some(from))?;
         }
         Ok(ret)
     }
 
+    /// A diagram of the board at board_number, numbering the moves from
+    /// move number_from on.
     pub fn render_board_to_latex(
         &self,
-        move_number: u32,
+        board_number: u32,
         number_from: Option<u32>,
     ) -> Result<String, Error> {
         let board = self
             .board_positions
-            .get(&move_number)
-            .ok_or(Error::InvalidBoardNumber(move_number.to_string()))?;
-        board.render_diagram(number_from)
+            .get(&board_number)
+            .ok_or(Error::InvalidBoardNumber(board_number.to_string()))?;
+        let mut ret = board.render_diagram_with_markup(number_from, &self.get_markup(board_number)?)?;
+        // Commentary for the moves first numbered in this diagram.
+        let from = number_from.unwrap_or(0);
+        for node in self.get_line(board_number)? {
+            let node_move_number = self.board_positions[&node].get_move_number();
+            if node_move_number < from {
+                continue;
+            }
+            let node = &self.nodes[node as usize];
+            if let Some(comment) = node.get_comment() {
+                let label = match (node.get_move(), node.get_name()) {
+                    (Some(m), Some(name)) => format!("{} {}", m.get_number(), name),
+                    (Some(m), None) => m.get_number().to_string(),
+                    (None, name) => name.unwrap_or_default(),
+                };
+                if !label.is_empty() {
+                    ret += &format!("\\textbf{{{}}}: ", latex_escape(&label));
+                }
+                ret += &latex_escape(&comment);
+                ret += "\\\\\n";
+            }
+        }
+        Ok(ret)
     }
 }
@@ -0,0 +1,293 @@
+/// The game-info properties of a record's root node. Values are kept as the
+/// SGF text with escapes removed; an empty string means the property is absent.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct GameInfo {
+    player_black: String,
+    rank_black: String,
+    team_black: String,
+    player_white: String,
+    rank_white: String,
+    team_white: String,
+    event: String,
+    round: String,
+    date: String,
+    place: String,
+    game_name: String,
+    game_comment: String,
+    opening: String,
+    time_limit: String,
+    overtime: String,
+    source: String,
+    user: String,
+    annotator: String,
+    copyright: String,
+}
+
+impl GameInfo {
+    pub fn new() -> GameInfo {
+        GameInfo::default()
+    }
+
+    // The field holding the game-info property ident, if it is one.
+    fn property_mut(&mut self, ident: &str) -> Option<&mut String> {
+        match ident {
+            "PB" => Some(&mut self.player_black),
+            "BR" => Some(&mut self.rank_black),
+            "BT" => Some(&mut self.team_black),
+            "PW" => Some(&mut self.player_white),
+            "WR" => Some(&mut self.rank_white),
+            "WT" => Some(&mut self.team_white),
+            "EV" => Some(&mut self.event),
+            "RO" => Some(&mut self.round),
+            "DT" => Some(&mut self.date),
+            "PC" => Some(&mut self.place),
+            "GN" => Some(&mut self.game_name),
+            "GC" => Some(&mut self.game_comment),
+            "ON" => Some(&mut self.opening),
+            "TM" => Some(&mut self.time_limit),
+            "OT" => Some(&mut self.overtime),
+            "SO" => Some(&mut self.source),
+            "US" => Some(&mut self.user),
+            "AN" => Some(&mut self.annotator),
+            "CP" => Some(&mut self.copyright),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn is_property(ident: &str) -> bool {
+        GameInfo::new().property_mut(ident).is_some()
+    }
+
+    /// Stores value under ident, returning false if ident is not a game-info property.
+    /// The value is raw SGF text, as returned by sgf_parser.
+    pub(crate) fn set_property(&mut self, ident: &str, value: &str) -> bool {
+        match self.property_mut(ident) {
+            Some(field) => {
+                *field = unescape_text(value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The properties that are set, in SGF syntax, e.g. "PB[Honinbo Shusaku]EV[Castle game]".
+    pub fn to_sgf(&self) -> String {
+        [
+            ("PB", &self.player_black),
+            ("BR", &self.rank_black),
+            ("BT", &self.team_black),
+            ("PW", &self.player_white),
+            ("WR", &self.rank_white),
+            ("WT", &self.team_white),
+            ("EV", &self.event),
+            ("RO", &self.round),
+            ("DT", &self.date),
+            ("PC", &self.place),
+            ("GN", &self.game_name),
+            ("GC", &self.game_comment),
+            ("ON", &self.opening),
+            ("TM", &self.time_limit),
+            ("OT", &self.overtime),
+            ("SO", &self.source),
+            ("US", &self.user),
+            ("AN", &self.annotator),
+            ("CP", &self.copyright),
+        ]
+        .iter()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(ident, value)| format!("{}[{}]", ident, escape_text(value)))
+        .collect()
+    }
+
+    /// Name of the black player.
+    pub fn get_player_black(&self) -> String {
+        self.player_black.clone()
+    }
+
+    pub fn set_player_black(&mut self, player_black: &str) {
+        self.player_black = player_black.to_string();
+    }
+
+    /// Rank of the black player.
+    pub fn get_rank_black(&self) -> String {
+        self.rank_black.clone()
+    }
+
+    pub fn set_rank_black(&mut self, rank_black: &str) {
+        self.rank_black = rank_black.to_string();
+    }
+
+    /// Team of the black player.
+    pub fn get_team_black(&self) -> String {
+        self.team_black.clone()
+    }
+
+    pub fn set_team_black(&mut self, team_black: &str) {
+        self.team_black = team_black.to_string();
+    }
+
+    /// Name of the white player.
+    pub fn get_player_white(&self) -> String {
+        self.player_white.clone()
+    }
+
+    pub fn set_player_white(&mut self, player_white: &str) {
+        self.player_white = player_white.to_string();
+    }
+
+    /// Rank of the white player.
+    pub fn get_rank_white(&self) -> String {
+        self.rank_white.clone()
+    }
+
+    pub fn set_rank_white(&mut self, rank_white: &str) {
+        self.rank_white = rank_white.to_string();
+    }
+
+    /// Team of the white player.
+    pub fn get_team_white(&self) -> String {
+        self.team_white.clone()
+    }
+
+    pub fn set_team_white(&mut self, team_white: &str) {
+        self.team_white = team_white.to_string();
+    }
+
+    /// Name of the event or tournament.
+    pub fn get_event(&self) -> String {
+        self.event.clone()
+    }
+
+    pub fn set_event(&mut self, event: &str) {
+        self.event = event.to_string();
+    }
+
+    /// Round number and type, e.g. "5 (final)".
+    pub fn get_round(&self) -> String {
+        self.round.clone()
+    }
+
+    pub fn set_round(&mut self, round: &str) {
+        self.round = round.to_string();
+    }
+
+    /// Date or dates the game was played, in SGF's ISO-based format.
+    pub fn get_date(&self) -> String {
+        self.date.clone()
+    }
+
+    pub fn set_date(&mut self, date: &str) {
+        self.date = date.to_string();
+    }
+
+    /// Where the game was played.
+    pub fn get_place(&self) -> String {
+        self.place.clone()
+    }
+
+    pub fn set_place(&mut self, place: &str) {
+        self.place = place.to_string();
+    }
+
+    /// Name used to identify the game.
+    pub fn get_game_name(&self) -> String {
+        self.game_name.clone()
+    }
+
+    pub fn set_game_name(&mut self, game_name: &str) {
+        self.game_name = game_name.to_string();
+    }
+
+    /// Extra information about the game as a whole.
+    pub fn get_game_comment(&self) -> String {
+        self.game_comment.clone()
+    }
+
+    pub fn set_game_comment(&mut self, game_comment: &str) {
+        self.game_comment = game_comment.to_string();
+    }
+
+    /// Opening played, e.g. "Chinese fuseki".
+    pub fn get_opening(&self) -> String {
+        self.opening.clone()
+    }
+
+    pub fn set_opening(&mut self, opening: &str) {
+        self.opening = opening.to_string();
+    }
+
+    /// Main time per player in seconds.
+    pub fn get_time_limit(&self) -> String {
+        self.time_limit.clone()
+    }
+
+    pub fn set_time_limit(&mut self, time_limit: &str) {
+        self.time_limit = time_limit.to_string();
+    }
+
+    /// Overtime system, e.g. "5x30 byo-yomi".
+    pub fn get_overtime(&self) -> String {
+        self.overtime.clone()
+    }
+
+    pub fn set_overtime(&mut self, overtime: &str) {
+        self.overtime = overtime.to_string();
+    }
+
+    /// Source of the record, e.g. a book or journal.
+    pub fn get_source(&self) -> String {
+        self.source.clone()
+    }
+
+    pub fn set_source(&mut self, source: &str) {
+        self.source = source.to_string();
+    }
+
+    /// Person or program who entered the record.
+    pub fn get_user(&self) -> String {
+        self.user.clone()
+    }
+
+    pub fn set_user(&mut self, user: &str) {
+        self.user = user.to_string();
+    }
+
+    /// Person who annotated the game.
+    pub fn get_annotator(&self) -> String {
+        self.annotator.clone()
+    }
+
+    pub fn set_annotator(&mut self, annotator: &str) {
+        self.annotator = annotator.to_string();
+    }
+
+    /// Copyright notice for the record.
+    pub fn get_copyright(&self) -> String {
+        self.copyright.clone()
+    }
+
+    pub fn set_copyright(&mut self, copyright: &str) {
+        self.copyright = copyright.to_string();
+    }
+}
+
+/// Escapes the characters SGF text values treat specially.
+pub(crate) fn escape_text(value: &str) -> String {
+    value.replace('\\', "\\\\").replace(']', "\\]")
+}
+
+/// Removes SGF text escapes, dropping escaped line breaks.
+pub(crate) fn unescape_text(value: &str) -> String {
+    let mut ret = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('\n') | None => {}
+                Some(c) => ret.push(c),
+            },
+            c => ret.push(c),
+        }
+    }
+    ret
+}
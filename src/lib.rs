@@ -1,12 +1,41 @@
 
 use thiserror::Error;
 use sgf_parser::*;
+use std::collections::BTreeSet;
 
 mod board;
 pub use self::board::Board;
 
 mod game;
 pub use self::game::Game;
+pub use self::game::GameResult;
+pub use self::game::TurnCheck;
+pub use self::game::BadMove;
+pub use self::game::Diagnostic;
+
+mod node;
+pub use self::node::Node;
+pub use self::node::NodeId;
+
+mod annotation;
+pub use self::annotation::Emphasis;
+pub use self::annotation::MoveAnnotation;
+pub use self::annotation::PositionAnnotation;
+
+mod markup;
+pub use self::markup::Markup;
+
+mod game_info;
+pub use self::game_info::GameInfo;
+
+mod score;
+pub use self::score::Score;
+pub use self::score::ScoringMethod;
+
+mod ruleset;
+pub use self::ruleset::Ruleset;
+pub use self::ruleset::SuicideRule;
+pub use self::ruleset::SuperkoRule;
 
 
 #[derive(Error, Debug)]
@@ -17,6 +46,14 @@ pub enum Error {
     ParseError(String),
     #[error("invalid move: {0}")]
     InvalidMove(String),
+    #[error("ko violation: {0}")]
+    KoViolation(String),
+    #[error("superko violation: {0}")]
+    SuperkoViolation(String),
+    #[error("out of turn: {0}")]
+    OutOfTurn(String),
+    #[error("invalid handicap: {0}")]
+    InvalidHandicap(String),
     #[error("invalid board number: {0}")]
     InvalidBoardNumber(String),
     /// Represents all other cases of `std::io::Error`.
@@ -66,7 +103,15 @@ pub struct Intersection {
 //  18 A1  B1  C1  D1  ... S1  T1
 
 impl Intersection {
-    fn new(row: u32, col: u32) -> Intersection {
+    pub fn row(&self) -> u32 {
+        self.row
+    }
+
+    pub fn col(&self) -> u32 {
+        self.col
+    }
+
+    pub fn new(row: u32, col: u32) -> Intersection {
         Intersection { row, col }
     }
 
@@ -74,6 +119,17 @@ impl Intersection {
         Intersection { row: row-1, col: col-1 }
     }
 
+    // SGF point value, column letter first. Lines 27 to 52 use upper case letters.
+    fn to_sgf(self) -> String {
+        [self.col, self.row]
+            .iter()
+            .map(|&n| match n {
+                0..=25 => (n as u8 + b'a') as char,
+                _ => (n as u8 - 26 + b'A') as char,
+            })
+            .collect()
+    }
+
     fn up(&self) -> Intersection {
         Intersection {
             row: self.row + 1,
@@ -102,15 +158,25 @@ impl Intersection {
         }
     }
 
-    fn to_coord(&self) -> String {
-        let mut ret: String = match self.col {
-            0..=7 => (self.col as u8 + b'a') as char,
-            _ => (self.col as u8 + b'b') as char,
-        }
-            .to_string();
+    fn to_coord(self) -> String {
+        let mut ret = column_label(self.col);
         ret += &(self.row + 1).to_string();
-        return ret;
+        ret
+    }
+}
+
+// Column letter as used by igo and most go software: 'i' is skipped, and
+// columns past 'z' take two letters, "aa", "ab" and so on. igo only knows the
+// single letters, so LaTeX output stops at 25 columns.
+fn column_label(col: u32) -> String {
+    const LETTERS: &[u8] = b"abcdefghjklmnopqrstuvwxyz";
+    let count = LETTERS.len() as u32;
+    let mut ret = String::new();
+    if col >= count {
+        ret.push(LETTERS[(col / count - 1) as usize] as char);
     }
+    ret.push(LETTERS[(col % count) as usize] as char);
+    ret
 }
 
 #[derive(Debug, Clone)]
@@ -122,30 +188,114 @@ pub enum PointState {
     },
 }
 
-#[derive(Debug, Clone, Copy)]
+/// A move in the game, either a stone played on an intersection or a pass.
+#[derive(Debug, Clone)]
 pub struct Move {
     move_number: u32,
-    intersection: Intersection,
+    intersection: Option<Intersection>,
     color: Color,
+    captures: Vec<Intersection>,
 }
 
 impl Move {
     pub fn get_number(&self) -> u32 { self.move_number }
-    pub fn row(&self) -> u32 {
-        self.intersection.row
+    /// The intersection played on, None for a pass.
+    pub fn get_intersection(&self) -> Option<Intersection> { self.intersection }
+    pub fn is_pass(&self) -> bool { self.intersection.is_none() }
+    pub fn row(&self) -> Option<u32> {
+        self.intersection.map(|i| i.row)
     }
-    pub fn col(&self) -> u32 {
-        self.intersection.col
+    pub fn col(&self) -> Option<u32> {
+        self.intersection.map(|i| i.col)
     }
     pub fn get_color(&self) -> Color { self.color }
+    /// The stones this move removed from the board.
+    pub fn get_captures(&self) -> Vec<Intersection> { self.captures.clone() }
 }
 
+/// A chain of orthogonally connected stones of one colour and the empty points next to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group {
+    color: Color,
+    stones: BTreeSet<Intersection>,
+    liberties: BTreeSet<Intersection>,
+}
+
+impl Group {
+    pub fn get_color(&self) -> Color { self.color }
+    pub fn get_stones(&self) -> Vec<Intersection> { self.stones.iter().cloned().collect() }
+    pub fn get_liberties(&self) -> Vec<Intersection> { self.liberties.iter().cloned().collect() }
+    pub fn contains(&self, intersection: Intersection) -> bool { self.stones.contains(&intersection) }
+    pub fn get_liberty_count(&self) -> u32 { self.liberties.len() as u32 }
+    pub fn is_in_atari(&self) -> bool { self.liberties.len() == 1 }
+}
 
 #[cfg(test)]
 mod tests {
     use crate::game::Game;
     use crate::Intersection;
     use crate::Color;
+    use crate::Error;
+    use crate::SuperkoRule;
+    use crate::SuicideRule;
+    use crate::PointState;
+    use crate::Board;
+    use crate::Ruleset;
+    use crate::ScoringMethod;
+    use crate::GameResult;
+    use crate::TurnCheck;
+    use crate::BadMove;
+    use crate::Markup;
+    use crate::Emphasis;
+    use crate::MoveAnnotation;
+    use crate::PositionAnnotation;
+    use sgf_parser::SgfToken;
+    use std::collections::BTreeSet;
+
+    fn board_from_rows(rows: &[&str]) -> Board {
+        let mut board = Board::new(rows.len() as u32);
+        for (r, row) in rows.iter().enumerate() {
+            for (c, point) in row.split_whitespace().enumerate() {
+                let intersection = Intersection::new(r as u32, c as u32);
+                match point {
+                    "X" => board.place_stone(intersection, Color::Black).unwrap(),
+                    "O" => board.place_stone(intersection, Color::White).unwrap(),
+                    _ => vec![],
+                };
+            }
+        }
+        board
+    }
+
+    // Plays the moves in turn from the end of the main line, returning the
+    // captures of each.
+    fn play_moves(game: &mut Game, moves: &[(Intersection, Color)]) -> Vec<Vec<Intersection>> {
+        moves
+            .iter()
+            .map(|(intersection, color)| {
+                let node = game.get_main_line_end();
+                game.place_stone(*intersection, *color, node).unwrap()
+            })
+            .collect()
+    }
+
+    // A 9x9 game in which black has just taken a ko at b2, so white may not
+    // retake at b2 and the corner a1 is suicide for white.
+    fn ko_game() -> Game {
+        let mut game = Game::new(9);
+        play_moves(&mut game, &[
+            (Intersection::new(1, 0), Color::Black),
+            (Intersection::new(0, 2), Color::White),
+            (Intersection::new(0, 1), Color::Black),
+            (Intersection::new(2, 2), Color::White),
+            (Intersection::new(2, 1), Color::Black),
+            (Intersection::new(1, 3), Color::White),
+            (Intersection::new(5, 5), Color::Black),
+            (Intersection::new(1, 1), Color::White),
+            (Intersection::new(1, 2), Color::Black),
+        ]);
+        game
+    }
 
     #[test]
     fn move_numbers() {
@@ -184,5 +334,950 @@ mod tests {
              .  .  .  .  .  .  .  .  .  .  .  .  .  .  .  .  .  .  .  \n\
              .  .  .  .  .  .  .  .  .  .  .  .  .  .  .  .  .  .  .  \n");
     }
-}
 
+    #[test]
+    fn ko() {
+        let mut game = ko_game();
+        let board = game.get_board(9).unwrap();
+        assert_eq!(board.get_ko_point(), Some(Intersection::new(1, 1)));
+
+        assert!(matches!(
+            game.place_stone(Intersection::new(1, 1), Color::White, 9),
+            Err(Error::KoViolation(_))
+        ));
+
+        game.place_stone(Intersection::new(6, 6), Color::White, 9).unwrap();
+        game.place_stone(Intersection::new(6, 7), Color::Black, 10).unwrap();
+        assert_eq!(game.get_board(11).unwrap().get_ko_point(), None);
+        game.place_stone(Intersection::new(1, 1), Color::White, 11).unwrap();
+        assert_eq!(game.get_board(12).unwrap().get_ko_point(), Some(Intersection::new(1, 2)));
+    }
+
+    #[test]
+    fn ko_survives_rejected_suicide() {
+        let mut board = ko_game().get_board(9).unwrap();
+        assert!(matches!(
+            board.place_stone(Intersection::new(0, 0), Color::White),
+            Err(Error::InvalidMove(_))
+        ));
+        assert_eq!(board.get_ko_point(), Some(Intersection::new(1, 1)));
+        assert!(!board.is_legal(Intersection::new(1, 1), Color::White));
+        assert!(matches!(
+            board.place_stone(Intersection::new(1, 1), Color::White),
+            Err(Error::KoViolation(_))
+        ));
+    }
+
+    #[test]
+    fn superko() {
+        let moves = [
+            (Intersection::new(0, 1), Color::Black),
+            (Intersection::new(1, 0), Color::White),
+            (Intersection::new(1, 2), Color::Black),
+            (Intersection::new(1, 1), Color::White),
+            (Intersection::new(0, 0), Color::Black),
+            (Intersection::new(0, 2), Color::White),
+        ];
+        let repeat = (Intersection::new(0, 1), Color::Black);
+
+        let mut game = Game::new(3);
+        play_moves(&mut game, &moves);
+        game.place_stone(repeat.0, repeat.1, 6).unwrap();
+        assert_eq!(game.get_board(7).unwrap().get_hash(), game.get_board(4).unwrap().get_hash());
+
+        let mut game = Game::new(3);
+        game.set_superko_rule(SuperkoRule::Positional);
+        play_moves(&mut game, &moves);
+        assert!(matches!(
+            game.place_stone(repeat.0, repeat.1, 6),
+            Err(Error::SuperkoViolation(_))
+        ));
+        assert_eq!(game.get_final_move_number(), 6);
+
+        // The repeated position had black to play, now white is to play.
+        game.set_superko_rule(SuperkoRule::Situational);
+        game.place_stone(repeat.0, repeat.1, 6).unwrap();
+    }
+
+    #[test]
+    fn suicide_single_stone() {
+        let mut game = Game::new(3);
+        play_moves(&mut game, &[
+            (Intersection::new(0, 1), Color::Black),
+            (Intersection::new(2, 2), Color::White),
+            (Intersection::new(1, 0), Color::Black),
+        ]);
+        assert!(matches!(
+            game.place_stone(Intersection::new(0, 0), Color::White, 3),
+            Err(Error::InvalidMove(_))
+        ));
+        assert_eq!(game.get_main_line_end(), 3);
+        let board = game.get_board(3).unwrap();
+        assert!(matches!(board.get_point(0, 0), Ok(PointState::Empty)));
+
+        game.set_suicide_rule(SuicideRule::Allowed);
+        let captures = game.place_stone(Intersection::new(0, 0), Color::White, 3).unwrap();
+        assert_eq!(captures, vec![Intersection::new(0, 0)]);
+        let board = game.get_board(4).unwrap();
+        assert!(matches!(board.get_point(0, 0), Ok(PointState::Empty)));
+        assert_eq!(board.get_prisoners_black(), 1);
+    }
+
+    #[test]
+    fn suicide_multiple_stones() {
+        let mut game = Game::new(3);
+        play_moves(&mut game, &[
+            (Intersection::new(1, 0), Color::Black),
+            (Intersection::new(0, 0), Color::White),
+            (Intersection::new(1, 1), Color::Black),
+            (Intersection::new(2, 2), Color::White),
+            (Intersection::new(0, 2), Color::Black),
+        ]);
+        assert!(matches!(
+            game.place_stone(Intersection::new(0, 1), Color::White, 5),
+            Err(Error::InvalidMove(_))
+        ));
+        assert_eq!(game.get_main_line_end(), 5);
+        let board = game.get_board(5).unwrap();
+        assert!(matches!(board.get_point(0, 1), Ok(PointState::Empty)));
+        assert!(matches!(board.get_point(0, 0), Ok(PointState::Filled { stone_color: Color::White, .. })));
+
+        game.set_suicide_rule(SuicideRule::Allowed);
+        let captures = game.place_stone(Intersection::new(0, 1), Color::White, 5).unwrap();
+        assert_eq!(captures.len(), 2);
+        let board = game.get_board(6).unwrap();
+        assert!(matches!(board.get_point(0, 0), Ok(PointState::Empty)));
+        assert!(matches!(board.get_point(0, 1), Ok(PointState::Empty)));
+        assert!(matches!(board.get_point(1, 0), Ok(PointState::Filled { stone_color: Color::Black, .. })));
+        assert_eq!(board.get_prisoners_black(), 2);
+    }
+
+    #[test]
+    fn captures() {
+        let game = Game::from_sgf_file("./resources/The_59th_Judan_Title_Match_3rd_game.sgf".to_string()).unwrap();
+        let board = game.get_board(game.get_main_line_end()).unwrap();
+        assert_eq!(board.to_ascii(),
+            ".  .  O  O  .  O  X  X  .  X  O  O  O  .  O  O  O  X  .  \n\
+             O  O  O  O  O  .  O  X  X  .  X  O  .  O  O  .  X  X  .  \n\
+             O  X  O  X  O  O  O  O  X  X  X  X  O  O  X  X  .  .  .  \n\
+             X  X  X  X  O  .  O  X  .  X  X  O  O  O  O  .  X  .  .  \n\
+             .  .  X  X  X  X  X  X  .  X  X  X  O  X  .  X  X  X  .  \n\
+             .  .  X  O  O  O  O  X  X  O  X  O  O  X  X  X  O  O  .  \n\
+             .  X  X  X  O  O  X  X  O  O  X  O  .  O  O  X  X  O  .  \n\
+             .  X  O  O  X  O  .  X  X  O  O  O  O  O  X  O  O  X  X  \n\
+             .  O  .  .  X  O  .  X  O  O  .  O  X  O  X  X  O  O  X  \n\
+             .  .  .  .  X  O  .  X  X  X  X  O  X  X  X  X  X  O  .  \n\
+             X  X  X  .  X  O  O  O  O  X  O  O  O  O  X  .  X  O  O  \n\
+             O  O  X  .  X  X  X  X  O  X  X  X  O  .  X  .  X  X  X  \n\
+             O  X  X  X  X  X  .  X  O  O  X  O  O  O  .  X  X  O  .  \n\
+             O  O  O  O  X  O  O  O  X  X  X  X  .  .  O  X  O  O  O  \n\
+             O  .  O  .  O  .  O  .  O  O  X  X  X  X  X  O  X  O  .  \n\
+             .  O  O  O  X  O  .  O  O  .  X  O  O  O  O  O  X  X  .  \n\
+             O  .  O  X  X  X  O  O  O  O  O  .  .  .  .  X  O  X  O  \n\
+             O  O  X  X  O  .  X  X  O  X  X  .  O  .  .  .  O  O  .  \n\
+             X  X  X  O  O  O  .  .  X  .  .  .  .  .  .  .  .  .  .  \n");
+    }
+
+    #[test]
+    fn prisoners() {
+        let mut game = Game::new(9);
+        game.set_suicide_rule(SuicideRule::Allowed);
+        let moves = [
+            (Intersection::new(0, 1), Color::Black),
+            (Intersection::new(0, 0), Color::White),
+            (Intersection::new(1, 1), Color::Black),
+            (Intersection::new(1, 0), Color::White),
+            (Intersection::new(5, 5), Color::Black),
+        ];
+        assert!(play_moves(&mut game, &moves).iter().all(|captures| captures.is_empty()));
+        let captures = game.place_stone(Intersection::new(2, 0), Color::Black, 5).unwrap();
+        assert_eq!(captures.len(), 2);
+        assert!(captures.contains(&Intersection::new(0, 0)));
+        assert!(captures.contains(&Intersection::new(1, 0)));
+
+        let board = game.get_board(6).unwrap();
+        assert_eq!(board.get_last_move().unwrap().get_captures(), captures);
+        assert_eq!(board.get_prisoners_black(), 2);
+        assert_eq!(board.get_prisoners_white(), 0);
+
+        // White's suicide in the corner counts towards black's prisoners.
+        game.place_stone(Intersection::new(8, 8), Color::White, 6).unwrap();
+        game.place_stone(Intersection::new(1, 0), Color::Black, 7).unwrap();
+        let captures = game.place_stone(Intersection::new(0, 0), Color::White, 8).unwrap();
+        assert_eq!(captures, vec![Intersection::new(0, 0)]);
+        let board = game.get_board(9).unwrap();
+        assert_eq!(board.get_prisoners_black(), 3);
+        assert_eq!(board.get_prisoners_white(), 0);
+    }
+
+    #[test]
+    fn groups() {
+        let mut game = Game::new(9);
+        let moves = [
+            (Intersection::new(0, 0), Color::Black),
+            (Intersection::new(1, 0), Color::White),
+            (Intersection::new(0, 1), Color::Black),
+            (Intersection::new(4, 4), Color::White),
+            (Intersection::new(4, 5), Color::Black),
+        ];
+        play_moves(&mut game, &moves);
+        let board = game.get_board(5).unwrap();
+
+        let corner = board.get_group(Intersection::new(0, 1)).unwrap();
+        assert_eq!(corner.get_color(), Color::Black);
+        assert_eq!(corner.get_stones(), vec![Intersection::new(0, 0), Intersection::new(0, 1)]);
+        assert_eq!(corner.get_liberties(), vec![Intersection::new(0, 2), Intersection::new(1, 1)]);
+        assert!(!corner.is_in_atari());
+
+        assert!(board.get_group(Intersection::new(2, 2)).is_none());
+        assert_eq!(board.get_liberty_count(Intersection::new(2, 2)), 0);
+        assert_eq!(board.get_liberty_count(Intersection::new(1, 0)), 2);
+        assert_eq!(board.get_liberty_count(Intersection::new(4, 4)), 3);
+
+        let groups = board.get_groups();
+        assert_eq!(groups.len(), 4);
+        assert!(groups[0].contains(Intersection::new(0, 0)));
+        assert_eq!(groups.iter().map(|g| g.get_stones().len()).sum::<usize>(), 5);
+    }
+
+    #[test]
+    fn passes() {
+        let tree = sgf_parser::parse("(;SZ[9];B[ee];W[];B[cc](;W[tt];B[])(;W[gg]))").unwrap();
+        let game = Game::from_game_tree(&tree).unwrap();
+        assert_eq!(game.get_final_move_number(), 5);
+        assert_eq!(game.get_last_node(), 6);
+
+        let board = game.get_board(2).unwrap();
+        assert!(board.get_last_move().unwrap().is_pass());
+        assert_eq!(board.get_last_move().unwrap().get_color(), Color::White);
+        assert_eq!(game.get_node(2).unwrap().get_children(), vec![3]);
+
+        let board = game.get_board(3).unwrap();
+        assert_eq!(board.get_last_move().unwrap().get_number(), 3);
+        assert_eq!(game.get_node(3).unwrap().get_children(), vec![4, 6]);
+
+        let board = game.get_board(5).unwrap();
+        assert!(board.get_last_move().unwrap().is_pass());
+        assert_eq!(board.get_last_move().unwrap().get_color(), Color::Black);
+        assert_eq!(board.get_last_move().unwrap().get_number(), 5);
+        assert_eq!(board.to_ascii(), game.get_board(3).unwrap().to_ascii());
+
+        let board = game.get_board(6).unwrap();
+        assert_eq!(board.get_last_move().unwrap().get_intersection(), Some(Intersection::new(6, 6)));
+    }
+
+    #[test]
+    fn legal_moves() {
+        let game = ko_game();
+        let board = game.get_board(9).unwrap();
+        // White may not retake the ko and the corner is suicide for white.
+        assert!(!board.is_legal(Intersection::new(1, 1), Color::White));
+        assert!(!board.is_legal(Intersection::new(0, 0), Color::White));
+        assert!(board.is_legal(Intersection::new(0, 0), Color::Black));
+        assert!(board.is_legal(Intersection::new(1, 1), Color::Black));
+        assert!(!board.is_legal(Intersection::new(5, 5), Color::Black));
+        assert_eq!(board.legal_moves(Color::White).len(), 81 - 8 - 2);
+        assert_eq!(board.legal_moves(Color::Black).len(), 81 - 8);
+
+        let game = Game::from_sgf_file("./resources/The_59th_Judan_Title_Match_3rd_game.sgf".to_string()).unwrap();
+        for bn in (0..=game.get_last_node()).step_by(30) {
+            let board = game.get_board(bn).unwrap();
+            for color in [Color::Black, Color::White].iter() {
+                for r in 0..19 {
+                    for c in 0..19 {
+                        let intersection = Intersection::new(r, c);
+                        assert_eq!(
+                            board.is_legal(intersection, *color),
+                            board.clone().place_stone(intersection, *color).is_ok()
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn territory_scoring() {
+        let board = board_from_rows(&[
+            ". X O . .",
+            ". X O . .",
+            ". X O O O",
+            "X X O . X",
+            ". X O . .",
+        ]);
+        let score = board.score_territory(&BTreeSet::new(), 6.5);
+        assert_eq!(score.get_territory_black().len(), 4);
+        assert_eq!(score.get_territory_white().len(), 4);
+        assert_eq!(score.to_result_string(), "W+6.5");
+
+        let mut dead_stones = BTreeSet::new();
+        board.mark_dead_group(&mut dead_stones, Intersection::new(3, 4));
+        let score = board.score_territory(&dead_stones, 6.5);
+        assert_eq!(score.get_territory_black().len(), 4);
+        assert_eq!(score.get_territory_white().len(), 8);
+        assert!(score.get_territory_white().contains(&Intersection::new(3, 4)));
+        assert_eq!(score.get_prisoners_white(), 1);
+        assert_eq!(score.get_black(), 4.0);
+        assert_eq!(score.get_white(), 15.5);
+        assert_eq!(score.get_winner(), Some(Color::White));
+        assert_eq!(score.to_result_string(), "W+11.5");
+
+        board.mark_dead_group(&mut dead_stones, Intersection::new(3, 4));
+        assert!(dead_stones.is_empty());
+    }
+
+    #[test]
+    fn seki_is_not_territory() {
+        let board = board_from_rows(&[
+            ". X . O . O X",
+            "X X O O O O X",
+            "O O X X X X X",
+            ". O X . . . .",
+            "O O X . . . .",
+            ". O X . . . .",
+            "O O X . . . .",
+        ]);
+        let score = board.score_territory(&BTreeSet::new(), 0.0);
+        assert!(!score.get_territory_black().contains(&Intersection::new(0, 0)));
+        assert!(!score.get_territory_white().contains(&Intersection::new(0, 4)));
+        assert_eq!(score.get_territory_black().len(), 16);
+        assert_eq!(score.get_territory_white(), vec![Intersection::new(3, 0), Intersection::new(5, 0)]);
+        assert_eq!(score.to_result_string(), "B+14");
+    }
+
+    #[test]
+    fn area_scoring() {
+        let board = board_from_rows(&[
+            ". X O . .",
+            ". X O . .",
+            ". X O O O",
+            "X X O . X",
+            ". X O . .",
+        ]);
+        // Tromp-Taylor: the black stone in white's area stays and makes the
+        // points around it neutral.
+        let score = board.score_area(&BTreeSet::new(), 7.5);
+        assert_eq!(score.get_stones_black(), 7);
+        assert_eq!(score.get_stones_white(), 7);
+        assert_eq!(score.get_black(), 11.0);
+        assert_eq!(score.get_white(), 18.5);
+        assert_eq!(score.to_result_string(), "W+7.5");
+
+        let mut dead_stones = BTreeSet::new();
+        board.mark_dead_group(&mut dead_stones, Intersection::new(3, 4));
+        let score = board.score_area(&dead_stones, 7.5);
+        assert_eq!(score.get_black(), 10.0);
+        assert_eq!(score.get_white(), 22.5);
+        assert_eq!(score.to_result_string(), "W+12.5");
+
+        // Area scoring counts the eyes of groups in seki.
+        let board = board_from_rows(&[
+            ". X . O . O X",
+            "X X O O O O X",
+            "O O X X X X X",
+            ". O X . . . .",
+            "O O X . . . .",
+            ". O X . . . .",
+            "O O X . . . .",
+        ]);
+        let score = board.score_area(&BTreeSet::new(), 0.0);
+        assert!(score.get_territory_black().contains(&Intersection::new(0, 0)));
+        assert!(score.get_territory_white().contains(&Intersection::new(0, 4)));
+    }
+
+    #[test]
+    fn score_final_position() {
+        let tree = sgf_parser::parse("(;SZ[5];B[ba];W[ca];B[bb];W[cb];B[bc];W[cc];B[bd];W[cd];B[be];W[ce])").unwrap();
+        let game = Game::from_game_tree(&tree).unwrap();
+        assert_eq!(game.get_main_line_end(), 10);
+        let score = game.score_territory(game.get_main_line_end(), &BTreeSet::new(), 0.5).unwrap();
+        assert_eq!(score.to_result_string(), "W+5.5");
+        let score = game.score_area(game.get_main_line_end(), &BTreeSet::new(), 7.5).unwrap();
+        assert_eq!(score.to_result_string(), "W+12.5");
+    }
+
+    #[test]
+    fn rulesets() {
+        let game = Game::from_sgf_file("./resources/game.sgf".to_string()).unwrap();
+        assert_eq!(game.get_ruleset(), Ruleset::Japanese);
+
+        let tree = sgf_parser::parse("(;SZ[5]RU[Chinese];B[ba];W[ca];B[bb];W[cb];B[bc];W[cc];B[bd];W[cd];B[be];W[ce])").unwrap();
+        let mut game = Game::from_game_tree(&tree).unwrap();
+        assert_eq!(game.get_ruleset(), Ruleset::Chinese);
+        assert_eq!(game.get_superko_rule(), SuperkoRule::Positional);
+        assert_eq!(game.get_suicide_rule(), SuicideRule::Forbidden);
+        let score = game.score(game.get_main_line_end(), &BTreeSet::new()).unwrap();
+        assert_eq!(score.get_method(), ScoringMethod::Area);
+        assert_eq!(score.to_result_string(), "W+12.5");
+
+        game.set_ruleset(Ruleset::Japanese);
+        let score = game.score(game.get_main_line_end(), &BTreeSet::new()).unwrap();
+        assert_eq!(score.to_result_string(), "W+11.5");
+
+        game.set_suicide_rule(SuicideRule::Allowed);
+        assert!(matches!(game.get_ruleset(), Ruleset::Custom { .. }));
+        assert_eq!(game.get_ruleset().get_name(), "");
+        assert_eq!(game.get_superko_rule(), SuperkoRule::None);
+        // A changed ruleset is not passed off as the stock one in SGF.
+        assert!(!game.to_sgf().contains("RU["));
+
+        let tree = sgf_parser::parse("(;SZ[9]RU[Tromp-Taylor];B[ee];W[aa])").unwrap();
+        let game = Game::from_game_tree(&tree).unwrap();
+        assert_eq!(game.get_ruleset(), Ruleset::TrompTaylor);
+        assert_eq!(game.get_suicide_rule(), SuicideRule::Allowed);
+        assert!(!game.get_ruleset().removes_dead_stones());
+        let dead: BTreeSet<Intersection> = [Intersection::new(0, 0)].iter().copied().collect();
+        let score = game.score(2, &dead).unwrap();
+        assert_eq!(score, game.score(2, &BTreeSet::new()).unwrap());
+        assert_eq!(score.to_result_string(), "W+7.5");
+        let renamed = Ruleset::TrompTaylor.customize("Club");
+        assert!(!renamed.removes_dead_stones());
+        assert!(!renamed.with_superko_rule(SuperkoRule::None).removes_dead_stones());
+
+        // Every position plays by the game's ruleset, the root included.
+        let tree = sgf_parser::parse("(;SZ[3]RU[NZ]AB[ba][ab];W[cc])").unwrap();
+        let mut game = Game::from_game_tree(&tree).unwrap();
+        assert!(game.get_board(0).unwrap().is_legal(Intersection::new(0, 0), Color::White));
+        assert!(game.get_board(1).unwrap().is_legal(Intersection::new(0, 0), Color::White));
+        game.set_ruleset(Ruleset::Chinese);
+        assert_eq!(game.get_board(1).unwrap().get_ruleset(), Ruleset::Chinese);
+        assert!(!game.get_board(0).unwrap().is_legal(Intersection::new(0, 0), Color::White));
+
+        let tree = sgf_parser::parse("(;SZ[9]RU[Club])").unwrap();
+        let game = Game::from_game_tree(&tree).unwrap();
+        assert_eq!(game.get_ruleset().get_name(), "Club");
+        assert_eq!(game.get_ruleset().get_scoring_method(), ScoringMethod::Territory);
+    }
+
+    #[test]
+    fn game_metadata() {
+        let game = Game::from_sgf_file("./resources/game.sgf".to_string()).unwrap();
+        assert_eq!(game.get_komi(), Some(-5.5));
+        assert_eq!(game.get_handicap(), 2);
+        assert_eq!(game.get_result(), Some(GameResult::Resign(Color::Black)));
+
+        let game = Game::from_sgf_file("./resources/The_59th_Judan_Title_Match_3rd_game.sgf".to_string()).unwrap();
+        assert_eq!(game.get_komi(), Some(6.5));
+        assert_eq!(game.get_handicap(), 0);
+        let result = game.get_result().unwrap();
+        assert_eq!(result, GameResult::Points(Color::Black, 4.5));
+        assert_eq!(result.get_winner(), Some(Color::Black));
+        assert_eq!(result.get_margin(), Some(4.5));
+        assert_eq!(result.to_sgf_string(), "B+4.5");
+
+        let tree = sgf_parser::parse("(;SZ[9]RE[Void])").unwrap();
+        let game = Game::from_game_tree(&tree).unwrap();
+        assert_eq!(game.get_komi(), None);
+        assert_eq!(game.get_result(), Some(GameResult::Void));
+
+        assert_eq!(GameResult::from_sgf("W+T"), GameResult::Time(Color::White));
+        assert_eq!(GameResult::from_sgf("B+"), GameResult::Win(Color::Black));
+        assert_eq!(GameResult::from_sgf("0"), GameResult::Draw);
+        assert_eq!(GameResult::from_sgf("?"), GameResult::Unknown("?".to_string()));
+        assert_eq!(GameResult::from_sgf("W+Forfeit").get_winner(), Some(Color::White));
+    }
+
+    #[test]
+    fn game_info() {
+        let game = Game::from_sgf_file("./resources/print1.sgf".to_string()).unwrap();
+        let info = game.get_info();
+        assert!(!info.get_event().is_empty());
+        assert!(!info.get_round().is_empty());
+        assert!(!info.get_source().is_empty());
+
+        let tree = sgf_parser::parse(
+            "(;SZ[9]PB[Shusaku]BR[4d]BT[Honinbo]PW[Gennan]WT[Inoue]EV[Castle game]RO[1]\
+             DT[1846-09-11]PC[Edo]GN[Ear-reddening game]GC[Famous]ON[Shusaku fuseki]TM[whole day]\
+             OT[none]SO[Invincible]US[me]AN[Gennan\\]]CP[public domain];B[cc];W[gg])",
+        )
+        .unwrap();
+        let game = Game::from_game_tree(&tree).unwrap();
+        let info = game.get_info();
+        assert_eq!(game.get_player_black(), "Shusaku");
+        assert_eq!(info.get_team_black(), "Honinbo");
+        assert_eq!(info.get_team_white(), "Inoue");
+        assert_eq!(info.get_round(), "1");
+        assert_eq!(info.get_date(), "1846-09-11");
+        assert_eq!(info.get_time_limit(), "whole day");
+        assert_eq!(info.get_opening(), "Shusaku fuseki");
+        assert_eq!(info.get_annotator(), "Gennan]");
+        assert_eq!(info.get_copyright(), "public domain");
+
+        let sgf = game.to_sgf();
+        let round_trip = Game::from_game_tree(&sgf_parser::parse(&sgf).unwrap()).unwrap();
+        assert_eq!(round_trip.get_info(), info);
+        assert_eq!(round_trip.get_board(2).unwrap().to_ascii(), game.get_board(2).unwrap().to_ascii());
+    }
+
+    #[test]
+    fn sgf_export() {
+        for file in ["game.sgf", "print1.sgf", "The_59th_Judan_Title_Match_3rd_game.sgf"] {
+            let game = Game::from_sgf_file(format!("./resources/{}", file)).unwrap();
+            let round_trip = Game::from_game_tree(&sgf_parser::parse(&game.to_sgf()).unwrap()).unwrap();
+            assert_eq!(round_trip.get_last_node(), game.get_last_node());
+            assert_eq!(round_trip.get_komi(), game.get_komi());
+            assert_eq!(round_trip.get_result(), game.get_result());
+            for bn in 0..=game.get_last_node() {
+                let board = game.get_board(bn).unwrap();
+                let round_trip_board = round_trip.get_board(bn).unwrap();
+                assert_eq!(round_trip_board.get_hash(), board.get_hash());
+                assert_eq!(round_trip.get_node(bn).unwrap().get_children(), game.get_node(bn).unwrap().get_children());
+            }
+        }
+
+        // A move in the root node is written once, as a move.
+        let game = Game::from_game_tree(&sgf_parser::parse("(;SZ[9]AB[aa]B[ee];W[cc])").unwrap()).unwrap();
+        let sgf = game.to_sgf();
+        assert!(sgf.contains("AB[aa]B[ee];W[cc]"));
+        let round_trip = Game::from_game_tree(&sgf_parser::parse(&sgf).unwrap()).unwrap();
+        assert_eq!(round_trip.get_board(1).unwrap().get_hash(), game.get_board(1).unwrap().get_hash());
+        assert_eq!(round_trip.get_final_move_number(), 2);
+
+        // Moves lenient loading skipped are left out, so the export loads strictly.
+        let tree = sgf_parser::parse("(;SZ[9];B[cc];W[cc];W[dd])").unwrap();
+        let (game, _) = Game::from_game_tree_lenient(&tree, BadMove::Skip).unwrap();
+        let sgf = game.to_sgf();
+        assert!(sgf.ends_with(";B[cc];;W[dd])\n"));
+        assert!(Game::from_game_tree(&sgf_parser::parse(&sgf).unwrap()).is_ok());
+
+        // RU is only written when the record or the caller states a ruleset.
+        let mut game = Game::from_game_tree(&sgf_parser::parse("(;SZ[9];B[ee])").unwrap()).unwrap();
+        assert!(!game.to_sgf().contains("RU["));
+        game.set_ruleset(Ruleset::Chinese);
+        assert!(game.to_sgf().contains("RU[Chinese]"));
+        let game = Game::from_game_tree(&sgf_parser::parse("(;SZ[9]RU[Japanese])").unwrap()).unwrap();
+        assert!(game.to_sgf().contains("RU[Japanese]"));
+    }
+
+    #[test]
+    fn rectangular_boards() {
+        // Black captures a white stone in the far corner of a 9 wide, 13 high board.
+        let tree = sgf_parser::parse("(;SZ[9:13];B[hm];W[im];B[il])").unwrap();
+        let game = Game::from_game_tree(&tree).unwrap();
+        assert_eq!(game.get_board_width(), 9);
+        assert_eq!(game.get_board_height(), 13);
+        let board = game.get_board(3).unwrap();
+        assert_eq!(board.get_width(), 9);
+        assert_eq!(board.get_height(), 13);
+        assert!(!board.is_square());
+        assert_eq!(board.get_last_move().unwrap().get_captures(), vec![Intersection::new(12, 8)]);
+        assert_eq!(board.get_prisoners_black(), 1);
+
+        let ascii = board.to_ascii();
+        assert_eq!(ascii.lines().count(), 13);
+        assert!(ascii.lines().all(|line| line.len() == 27));
+        assert!(ascii.lines().last().unwrap().ends_with("X  .  "));
+        assert!(game.render_board_to_latex(3, None).unwrap().contains("\\showgoban[a1,j13]"));
+        assert!(game.to_sgf().starts_with("(;FF[4]GM[1]SZ[9:13]"));
+
+        // Square boards up to 19x19 show the whole goban as before.
+        let game = Game::from_game_tree(&sgf_parser::parse("(;SZ[9];B[ee])").unwrap()).unwrap();
+        assert_eq!(
+            game.render_board_to_latex(1, None).unwrap(),
+            "\\black[1]{e5}\n\n\\begin{center}\n\\vflipgoban\n\\shortstack{\\showfullgoban \\\\ From move 0}\n\\end{center}\n\\cleargoban\n"
+        );
+
+        let mut board = Board::with_dimensions(7, 7);
+        assert_eq!(board.legal_moves(Color::Black).len(), 49);
+        assert!(board.place_stone(Intersection::new(6, 6), Color::Black).is_ok());
+    }
+
+    #[test]
+    fn large_boards() {
+        let tree = sgf_parser::parse("(;SZ[52];B[zz];W[AA];B[ZZ];W[aZ])").unwrap();
+        let game = Game::from_game_tree(&tree).unwrap();
+        assert_eq!(game.get_board_size(), 52);
+        let board = game.get_board(4).unwrap();
+        assert_eq!(board.get_stones(Color::Black), vec![Intersection::new(25, 25), Intersection::new(51, 51)]);
+        assert_eq!(board.get_stones(Color::White), vec![Intersection::new(26, 26), Intersection::new(51, 0)]);
+        assert_eq!(Intersection::new(51, 51).to_coord(), "bb52");
+        assert_eq!(Intersection::new(0, 24).to_coord(), "z1");
+        assert_eq!(Intersection::new(0, 25).to_coord(), "aa1");
+
+        let sgf = game.to_sgf();
+        assert!(sgf.contains(";B[zz];W[AA];B[ZZ];W[aZ]"));
+        let round_trip = Game::from_game_tree(&sgf_parser::parse(&sgf).unwrap()).unwrap();
+        assert_eq!(round_trip.get_board(4).unwrap().get_hash(), board.get_hash());
+
+        let ascii = Game::new(21).get_board(0).unwrap().to_ascii_with_coordinates();
+        assert!(ascii.starts_with("   a  b  c  d  e  f  g  h  j  k"));
+        assert!(ascii.lines().next().unwrap().ends_with("v"));
+        assert!(ascii.lines().last().unwrap().starts_with("21 .  "));
+
+        assert!(matches!(game.render_board_to_latex(4, None), Err(Error::Other(_))));
+        let game = Game::from_game_tree(&sgf_parser::parse("(;SZ[25];B[yy])").unwrap()).unwrap();
+        assert!(game.render_board_to_latex(1, None).unwrap().contains("\\black[1]{z25}"));
+
+        let tree = sgf_parser::parse("(;SZ[53])").unwrap();
+        assert!(matches!(Game::from_game_tree(&tree), Err(Error::ParseError(_))));
+    }
+
+    #[test]
+    fn fixed_handicap() {
+        let mut game = Game::new(19);
+        let stones = game.place_handicap(2).unwrap();
+        assert_eq!(stones, vec![Intersection::new(3, 15), Intersection::new(15, 3)]);
+        assert_eq!(game.get_handicap(), 2);
+        // Matches HA[2]AB[pd][dp] as written by IGS.
+        let recorded = Game::from_sgf_file("./resources/game.sgf".to_string()).unwrap();
+        assert_eq!(game.get_board(0).unwrap().get_hash(), recorded.get_board(0).unwrap().get_hash());
+
+        let mut game = Game::new(13);
+        let stones = game.place_handicap(5).unwrap();
+        assert!(stones.contains(&Intersection::new(6, 6)));
+        assert_eq!(game.get_board(0).unwrap().get_stones(Color::Black).len(), 5);
+
+        let mut game = Game::new(9);
+        let stones = game.place_handicap(8).unwrap();
+        assert!(!stones.contains(&Intersection::new(4, 4)));
+        assert!(stones.contains(&Intersection::new(2, 4)));
+        assert_eq!(game.get_board(0).unwrap().get_stones(Color::Black).len(), 8);
+
+        assert!(matches!(Game::new(19).place_handicap(1), Err(Error::InvalidHandicap(_))));
+        assert!(matches!(Game::new(19).place_handicap(10), Err(Error::InvalidHandicap(_))));
+        assert!(matches!(Game::new(15).place_handicap(4), Err(Error::InvalidHandicap(_))));
+        assert!(matches!(Game::with_dimensions(9, 13).place_handicap(2), Err(Error::InvalidHandicap(_))));
+
+        // Nothing is placed when one of the points is taken.
+        let mut game = Game::new(19);
+        game.add_setup_stone(Intersection::new(15, 3), Color::White).unwrap();
+        assert!(matches!(game.place_handicap(2), Err(Error::InvalidMove(_))));
+        assert_eq!(game.get_board(0).unwrap().get_stones(Color::Black), vec![]);
+        assert_eq!(game.get_handicap(), 0);
+        assert_eq!(game.to_play(0).unwrap(), Color::Black);
+    }
+
+    #[test]
+    fn setup_stones() {
+        // Black to kill: the white corner group's last liberty is at aa.
+        let tree = sgf_parser::parse("(;FF[4]SZ[9]AB[ca][cb][bc][ac]AW[ba][bb][ab]C[Black to kill];B[aa])").unwrap();
+        let game = Game::from_game_tree(&tree).unwrap();
+        let board = game.get_board(0).unwrap();
+        assert_eq!(board.get_stones(Color::White), vec![Intersection::new(0, 1), Intersection::new(1, 0), Intersection::new(1, 1)]);
+        assert_eq!(board.get_stones(Color::Black).len(), 4);
+        let board = game.get_board(1).unwrap();
+        assert_eq!(board.get_last_move().unwrap().get_captures().len(), 3);
+        assert!(board.get_stones(Color::White).is_empty());
+        assert!(game.to_sgf().contains("AW[ba][ab][bb]"));
+
+        // Compressed point lists and AE.
+        let tree = sgf_parser::parse("(;SZ[9]AB[aa:cc]AW[ea:eb];AE[bb][ba][ea];W[bb])").unwrap();
+        let game = Game::from_game_tree(&tree).unwrap();
+        let board = game.get_board(game.get_main_line_end()).unwrap();
+        assert_eq!(board.get_stones(Color::Black).len(), 7);
+        assert_eq!(board.get_stones(Color::White), vec![Intersection::new(1, 1), Intersection::new(1, 4)]);
+
+        // Setup stones never capture, whatever order the properties come in.
+        for sgf in ["(;SZ[9]AW[aa]AB[ab][ba])", "(;SZ[9]AB[ab][ba]AW[aa])"] {
+            let game = Game::from_game_tree(&sgf_parser::parse(sgf).unwrap()).unwrap();
+            let board = game.get_board(0).unwrap();
+            assert_eq!(board.get_stones(Color::White), vec![Intersection::new(0, 0)]);
+            assert_eq!(board.get_stones(Color::Black).len(), 2);
+            assert_eq!(board.get_prisoners_white(), 0);
+        }
+
+        let mut game = Game::new(9);
+        game.add_setup_stone(Intersection::new(4, 4), Color::Black).unwrap();
+        game.add_setup_stone(Intersection::new(4, 4), Color::White).unwrap();
+        assert_eq!(game.get_board(0).unwrap().get_stones(Color::White), vec![Intersection::new(4, 4)]);
+        game.clear_setup_point(Intersection::new(4, 4));
+        assert_eq!(game.get_board(0).unwrap().get_hash(), Board::new(9).get_hash());
+    }
+
+    #[test]
+    fn setup_positions() {
+        let tree = sgf_parser::parse("(;SZ[9]AB[cc];W[gg](;AW[dd]AE[cc];B[ee])(;B[cd]))").unwrap();
+        let game = Game::from_game_tree(&tree).unwrap();
+        assert_eq!(game.get_node(1).unwrap().get_children(), vec![2, 4]);
+        let board = game.get_board(2).unwrap();
+        assert!(board.is_setup());
+        assert_eq!(game.get_node(2).unwrap().get_parent(), Some(1));
+        assert_eq!(board.get_last_move().unwrap().get_number(), 1);
+        assert_eq!(board.get_stones(Color::Black), vec![]);
+        assert_eq!(board.get_stones(Color::White), vec![Intersection::new(3, 3), Intersection::new(6, 6)]);
+        let board = game.get_board(3).unwrap();
+        assert!(!board.is_setup());
+        assert_eq!(board.get_last_move().unwrap().get_number(), 2);
+        assert_eq!(game.get_board(4).unwrap().get_stones(Color::Black).len(), 2);
+        assert!(game.render_board_to_latex(2, None).unwrap().contains("\\white{d4}"));
+        let tree = sgf_parser::parse("(;SZ[9]HA[2]AB[cc][gg];W[ee])").unwrap();
+        assert!(!Game::from_game_tree(&tree).unwrap().get_board(0).unwrap().is_setup());
+
+        // Problem variations that start with setup rather than a move.
+        let tree = sgf_parser::parse("(;SZ[9]AB[ba](;AW[aa])(;AW[bb]AB[aa]))").unwrap();
+        let game = Game::from_game_tree(&tree).unwrap();
+        assert_eq!(game.get_node(0).unwrap().get_children(), vec![1, 2]);
+        assert!(game.get_board(2).unwrap().is_setup());
+        assert!(game.get_board(1).unwrap().get_last_move().is_none());
+
+        for sgf in ["(;SZ[9]AB[cc];W[gg](;AW[dd]AE[cc];B[ee])(;B[cd]))", "(;SZ[9]AB[ba](;AW[aa])(;AW[bb]AB[aa]))"] {
+            let game = Game::from_game_tree(&sgf_parser::parse(sgf).unwrap()).unwrap();
+            let round_trip = Game::from_game_tree(&sgf_parser::parse(&game.to_sgf()).unwrap()).unwrap();
+            assert_eq!(round_trip.get_last_node(), game.get_last_node());
+            for bn in 0..=game.get_last_node() {
+                assert_eq!(round_trip.get_board(bn).unwrap().get_hash(), game.get_board(bn).unwrap().get_hash());
+                assert_eq!(round_trip.get_board(bn).unwrap().is_setup(), game.get_board(bn).unwrap().is_setup());
+            }
+        }
+    }
+
+    #[test]
+    fn to_play() {
+        let game = Game::from_sgf_file("./resources/game.sgf".to_string()).unwrap();
+        assert_eq!(game.to_play(0).unwrap(), Color::White);
+        assert_eq!(game.to_play(1).unwrap(), Color::Black);
+        assert!(game.get_warnings().is_empty());
+        let game = Game::from_sgf_file("./resources/The_59th_Judan_Title_Match_3rd_game.sgf".to_string()).unwrap();
+        assert_eq!(game.to_play(0).unwrap(), Color::Black);
+        assert_eq!(game.get_board(1).unwrap().to_play(), Color::White);
+        assert!(game.get_warnings().is_empty());
+
+        let mut game = Game::new(9);
+        game.place_handicap(2).unwrap();
+        assert_eq!(game.to_play(0).unwrap(), Color::White);
+
+        // PL, both in the root and part way through as its own position.
+        let tree = sgf_parser::parse("(;SZ[9]AB[aa]PL[W];W[bb];B[cc];PL[B];B[dd])").unwrap();
+        let game = Game::from_game_tree(&tree).unwrap();
+        assert_eq!(game.to_play(0).unwrap(), Color::White);
+        assert!(game.get_board(3).unwrap().is_setup());
+        assert_eq!(game.to_play(3).unwrap(), Color::Black);
+        assert_eq!(game.to_play(4).unwrap(), Color::White);
+        assert!(game.get_warnings().is_empty());
+        assert!(game.to_sgf().contains(";PL[B];B[dd]"));
+
+        let tree = sgf_parser::parse("(;SZ[9];B[aa];B[bb])").unwrap();
+        let game = Game::from_game_tree(&tree).unwrap();
+        assert_eq!(game.get_warnings(), vec!["black played move 2 but white was to play".to_string()]);
+        assert_eq!(game.get_final_move_number(), 2);
+        let game = Game::from_game_tree_with_turn_check(&tree, TurnCheck::Allow).unwrap();
+        assert!(game.get_warnings().is_empty());
+        assert!(matches!(
+            Game::from_game_tree_with_turn_check(&tree, TurnCheck::Error),
+            Err(Error::OutOfTurn(_))
+        ));
+
+        let mut game = Game::new(9);
+        game.set_turn_check(TurnCheck::Error);
+        assert!(matches!(game.pass(Color::White, 0), Err(Error::OutOfTurn(_))));
+        assert_eq!(game.get_final_move_number(), 0);
+    }
+
+    #[test]
+    fn lenient_loading() {
+        let tree = sgf_parser::parse("(;SZ[9];B[cc];W[cc];W[dd](;B[zz])(;B[ee]))").unwrap();
+        assert!(matches!(Game::from_game_tree(&tree), Err(Error::InvalidMove(_))));
+
+        let (game, diagnostics) = Game::from_game_tree_lenient(&tree, BadMove::Skip).unwrap();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].get_node_path(), vec![2]);
+        assert_eq!(diagnostics[0].get_move_number(), 2);
+        assert_eq!(diagnostics[0].to_string(), "node 2, move 2: invalid move: Point already filled; skipped");
+        assert_eq!(diagnostics[1].get_node_path(), vec![0, 0]);
+        assert_eq!(diagnostics[1].get_move_number(), 3);
+        // Nodes whose moves were skipped stay in the tree, holding the position before them.
+        assert_eq!(game.get_last_node(), 5);
+        assert_eq!(game.get_final_move_number(), 2);
+        assert!(game.get_node(2).unwrap().get_move().is_none());
+        assert_eq!(game.get_board(2).unwrap().get_hash(), game.get_board(1).unwrap().get_hash());
+        let board = game.get_board(5).unwrap();
+        assert_eq!(board.get_stones(Color::Black), vec![Intersection::new(2, 2), Intersection::new(4, 4)]);
+        assert_eq!(board.get_stones(Color::White), vec![Intersection::new(3, 3)]);
+
+        let (game, diagnostics) = Game::from_game_tree_lenient(&tree, BadMove::ForcePlace).unwrap();
+        assert_eq!(diagnostics.len(), 3);
+        assert!(diagnostics[0].get_reason().ends_with("placed anyway"));
+        // The forced move leaves white to have played twice in a row.
+        assert_eq!(diagnostics[1].to_string(), "node 3, move 3: white played move 3 but black was to play");
+        assert!(diagnostics[2].get_reason().ends_with("skipped"));
+        assert!(game.get_warnings().is_empty());
+        let board = game.get_board(2).unwrap();
+        assert_eq!(board.get_stones(Color::Black), vec![]);
+        assert_eq!(board.get_stones(Color::White), vec![Intersection::new(2, 2)]);
+
+        let tree = sgf_parser::parse("(;SZ[9];B[ba];W[ee];B[ab];W[aa])").unwrap();
+        let (game, diagnostics) = Game::from_game_tree_lenient(&tree, BadMove::ForcePlace).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].get_reason(), "invalid move: Suicide; placed anyway and removed as suicide");
+        assert_eq!(game.get_board(4).unwrap().get_stones(Color::White), vec![Intersection::new(4, 4)]);
+
+        // Skipped setup leaves the position as it was, AE included.
+        let tree = sgf_parser::parse("(;SZ[9];B[ee];AE[ee]AB[zz])").unwrap();
+        let (game, diagnostics) = Game::from_game_tree_lenient(&tree, BadMove::Skip).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].get_reason().ends_with("setup skipped"));
+        assert_eq!(game.get_board(2).unwrap().get_hash(), game.get_board(1).unwrap().get_hash());
+        assert!(!game.get_board(2).unwrap().is_setup());
+    }
+
+    #[test]
+    fn game_tree() {
+        let sgf = "(;SZ[9]C[Start];B[ee];C[Comment only](;W[cc]XX[kept])(;W[gg];B[cc]))";
+        let game = Game::from_game_tree(&sgf_parser::parse(sgf).unwrap()).unwrap();
+        assert_eq!(game.get_last_node(), 5);
+        assert_eq!(game.get_main_line_end(), 3);
+        assert_eq!(game.get_final_move_number(), 2);
+        let root = game.get_node(0).unwrap();
+        assert_eq!(root.get_parent(), None);
+        assert!(root.get_move().is_none());
+        assert_eq!(root.get_children(), vec![1]);
+
+        let node = game.get_node(2).unwrap();
+        assert!(node.get_move().is_none());
+        assert_eq!(node.get_properties(), vec![SgfToken::Comment("Comment only".to_string())]);
+        assert_eq!(node.get_children(), vec![3, 4]);
+        assert_eq!(game.get_board(2).unwrap().get_hash(), game.get_board(1).unwrap().get_hash());
+        assert_eq!(game.to_play(2).unwrap(), Color::White);
+
+        let node = game.get_node(5).unwrap();
+        assert_eq!(node.get_parent(), Some(4));
+        assert_eq!(node.get_move().unwrap().get_number(), 3);
+        assert_eq!(game.get_main_line_end(), 3);
+
+        let exported = game.to_sgf();
+        assert!(exported.contains("C[Start];B[ee];C[Comment only](;W[cc]XX[kept])(;W[gg];B[cc]))"));
+
+        let mut game = Game::new(9);
+        game.place_stone(Intersection::new(4, 4), Color::Black, 0).unwrap();
+        game.pass(Color::White, 1).unwrap();
+        assert_eq!(game.get_node(1).unwrap().get_move().unwrap().get_intersection(), Some(Intersection::new(4, 4)));
+        assert!(game.get_node(2).unwrap().get_move().unwrap().is_pass());
+        assert!(game.to_sgf().ends_with(";B[ee];W[])\n"));
+
+        // Each move of a node holding several gets a node and position of its own.
+        let sgf = "(;SZ[9];B[ee]W[cc]C[Two moves];B[gg])";
+        let game = Game::from_game_tree(&sgf_parser::parse(sgf).unwrap()).unwrap();
+        assert_eq!(game.get_final_move_number(), 3);
+        assert_eq!(game.get_line(3).unwrap(), vec![0, 1, 2, 3]);
+        assert_eq!(game.get_comment(1).unwrap(), Some("Two moves".to_string()));
+        assert_eq!(game.get_board(2).unwrap().get_last_move().unwrap().get_color(), Color::White);
+        assert!(game.to_sgf().ends_with(";B[ee]C[Two moves];W[cc];B[gg])\n"));
+
+        // A comment-only node does not count as a move when making diagrams.
+        let sgf = "(;SZ[9];B[ee];C[Comment only];W[cc];B[gg])";
+        let game = Game::from_game_tree(&sgf_parser::parse(sgf).unwrap()).unwrap();
+        assert_eq!(game.get_final_move_number(), 3);
+        assert_eq!(game.get_main_line_end(), 4);
+        let latex = game.render_to_latex(1).unwrap();
+        assert_eq!(latex.matches("From move").count(), 3);
+        assert!(latex.contains("From move 2}"));
+        assert!(latex.contains("From move 3}"));
+        assert!(!latex.contains("From move 1}"));
+        assert_eq!(latex.matches("Comment only").count(), 1);
+    }
+
+    #[test]
+    fn comments() {
+        let sgf = "(;SZ[9]C[Even game];B[ee]N[Tengen]C[Bold \\] move];W[cc]C[50% of pros play _this_])";
+        let game = Game::from_game_tree(&sgf_parser::parse(sgf).unwrap()).unwrap();
+        assert_eq!(game.get_comment(0).unwrap(), Some("Even game".to_string()));
+        assert_eq!(game.get_name(1).unwrap(), Some("Tengen".to_string()));
+        assert_eq!(game.get_comment(1).unwrap(), Some("Bold ] move".to_string()));
+        assert_eq!(game.get_name(2).unwrap(), None);
+        assert_eq!(game.get_line(2).unwrap(), vec![0, 1, 2]);
+
+        let latex = game.render_board_to_latex(2, None).unwrap();
+        assert!(latex.ends_with("Even game\\\\\n\\textbf{1 Tengen}: Bold ] move\\\\\n\\textbf{2}: 50\\% of pros play \\_this\\_\\\\\n"));
+        // Comments start with the first numbered move.
+        let latex = game.render_board_to_latex(2, Some(2)).unwrap();
+        assert!(latex.contains("\\white[2]{c3}"));
+        assert!(!latex.contains("Tengen"));
+        assert!(latex.contains("\\textbf{2}"));
+        let latex = game.render_board_to_latex(2, Some(1)).unwrap();
+        assert!(latex.contains("Tengen"));
+
+        let game = Game::from_sgf_file("./resources/game.sgf".to_string()).unwrap();
+        assert!((0..=game.get_last_node()).any(|node| game.get_comment(node).unwrap().is_some()));
+        assert!(game.to_sgf().contains("C["));
+    }
+
+    #[test]
+    fn annotations() {
+        let sgf = "(;SZ[9]GB[2];B[ee]TE[1]HO[1];W[ff]BM[2]V[-3.5];B[dd]DO[]UC[1](;W[cc]IT[])(;W[gg]BM[1]GW[1]))";
+        let game = Game::from_game_tree(&sgf_parser::parse(sgf).unwrap()).unwrap();
+        let node = game.get_node(2).unwrap();
+        assert_eq!(node.get_move_annotation(), Some(MoveAnnotation::BadMove(Emphasis::Strong)));
+        assert_eq!(node.get_value(), Some(-3.5));
+        assert_eq!(game.get_node(0).unwrap().get_move_annotation(), None);
+        assert_eq!(game.get_hotspots(), vec![(1, Emphasis::Normal)]);
+        assert_eq!(game.get_position_annotations(), vec![
+            (0, PositionAnnotation::GoodForBlack(Emphasis::Strong)),
+            (3, PositionAnnotation::Unclear(Emphasis::Normal)),
+            (5, PositionAnnotation::GoodForWhite(Emphasis::Normal)),
+        ]);
+
+        let mistakes: Vec<_> = game.get_move_annotations().into_iter().filter(|(_, a)| a.is_mistake()).map(|(n, _)| n).collect();
+        assert_eq!(mistakes, vec![2, 3, 5]);
+        assert_eq!(game.get_move_annotations()[0], (1, MoveAnnotation::Tesuji(Emphasis::Normal)));
+        assert!(game.to_sgf().contains("BM[2]"));
+    }
+
+    #[test]
+    fn markup() {
+        let sgf = "(;SZ[5]DD[aa:ab];B[cc]CR[cc]SQ[aa:ba]TR[dd]LB[ee:A]MA[bd]AR[aa:cc]LN[ae:ea];W[dc]LB[cd:\\]];B[bb]DD[])";
+        let game = Game::from_game_tree(&sgf_parser::parse(sgf).unwrap()).unwrap();
+        let markup = game.get_markup(1).unwrap();
+        assert_eq!(markup.get_circles(), vec![Intersection::new(2, 2)]);
+        assert_eq!(markup.get_squares(), vec![Intersection::new(0, 0), Intersection::new(0, 1)]);
+        assert_eq!(markup.get_labels(), vec![(Intersection::new(4, 4), "A".to_string())]);
+        assert_eq!(markup.get_arrows(), vec![(Intersection::new(0, 0), Intersection::new(2, 2))]);
+        assert_eq!(markup.get_dimmed().len(), 2);
+        assert!(game.get_node(1).unwrap().get_markup().get_dimmed().is_empty());
+        assert_eq!(game.get_markup(2).unwrap().get_labels(), vec![(Intersection::new(3, 2), "]".to_string())]);
+        assert!(game.get_markup(3).unwrap().is_empty());
+        assert_eq!(game.get_markup(0).unwrap(), game.get_node(0).unwrap().get_markup());
+        assert_ne!(game.get_markup(0).unwrap(), Markup::default());
+
+        let ascii = game.get_board(1).unwrap().to_ascii_with_markup(&markup, false);
+        assert_eq!(ascii.lines().next().unwrap(), ".# .# .  .  .  ");
+        assert!(ascii.contains("X@"));
+        assert!(ascii.contains(".A"));
+        assert!(ascii.ends_with("arrow a1 -> c3\nline a5 -> e1\n"));
+
+        let latex = game.render_board_to_latex(1, None).unwrap();
+        assert!(latex.contains("\\gobansymbol{a1}{\\igosquare}"));
+        assert!(latex.contains("\\gobansymbol{e5}{A}"));
+        assert!(!latex.contains("\\gobansymbol{c3}"));
+        assert!(latex.contains("Arrow from a1 to c3\\\\"));
+
+        // CR, which sgf_parser reads as a copyright, survives export.
+        let game = Game::from_game_tree(&sgf_parser::parse("(;SZ[9];B[ee]CR[aa][bb])").unwrap()).unwrap();
+        assert!(game.to_sgf().contains(";B[ee]CR[aa][bb]"));
+        let round_trip = Game::from_game_tree(&sgf_parser::parse(&game.to_sgf()).unwrap()).unwrap();
+        let circles = vec![Intersection::new(0, 0), Intersection::new(1, 1)];
+        assert_eq!(game.get_markup(1).unwrap().get_circles(), circles);
+        assert_eq!(round_trip.get_markup(1).unwrap().get_circles(), circles);
+
+        // Markup keeps out of the way of the letters for captured stones.
+        let sgf = "(;SZ[5];B[ab];W[aa];B[ba]CR[aa]LB[ee:A])";
+        let game = Game::from_game_tree(&sgf_parser::parse(sgf).unwrap()).unwrap();
+        let latex = game.render_board_to_latex(3, None).unwrap();
+        assert_eq!(latex.matches("\\gobansymbol{a1}").count(), 1);
+        assert!(latex.contains("\\gobansymbol{a1}{B}"));
+        assert!(latex.contains("\\gobansymbol{e5}{A}"));
+        assert!(latex.contains("2 at B\\\\"));
+    }
+
+    #[test]
+    fn time_left() {
+        let sgf = "(;SZ[9]TM[60];B[ee]BL[55];W[cc]WL[58.5]OW[5];B[dd]BL[40];W[ff]WL[30]OW[4];B[gg];W[hh]WL[29]OW[3])";
+        let game = Game::from_game_tree(&sgf_parser::parse(sgf).unwrap()).unwrap();
+        let node = game.get_node(2).unwrap();
+        assert_eq!(node.get_time_left(Color::White), Some(58.5));
+        assert_eq!(node.get_overtime_moves(Color::White), Some(5));
+        assert_eq!(node.get_time_left(Color::Black), None);
+        assert_eq!(game.get_time_spent(6).unwrap(), vec![
+            (1, Color::Black, 5.0),
+            (2, Color::White, 1.5),
+            (3, Color::Black, 15.0),
+            (4, Color::White, 28.5),
+            (6, Color::White, 1.0),
+        ]);
+        assert!(game.to_sgf().contains("WL[58.5]"));
+
+        let game = Game::from_sgf_file("./resources/game.sgf".to_string()).unwrap();
+        let spent = game.get_time_spent(game.get_main_line_end()).unwrap();
+        assert!(!spent.is_empty());
+        assert!(spent.iter().all(|(_, _, time)| *time >= 0.0));
+    }
+}
@@ -0,0 +1,163 @@
+use sgf_parser::SgfToken;
+
+use super::game::latex_escape;
+use super::game::point_list;
+use super::game_info::unescape_text;
+use super::Intersection;
+
+/// The board markup of a node: the CR, SQ, TR, MA, SL, LB, AR, LN and DD
+/// properties.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Markup {
+    circles: Vec<Intersection>,
+    squares: Vec<Intersection>,
+    triangles: Vec<Intersection>,
+    crosses: Vec<Intersection>,
+    selected: Vec<Intersection>,
+    labels: Vec<(Intersection, String)>,
+    arrows: Vec<(Intersection, Intersection)>,
+    lines: Vec<(Intersection, Intersection)>,
+    dimmed: Vec<Intersection>,
+}
+
+impl Markup {
+    // Malformed values are ignored, as with other properties we do not play.
+    pub(crate) fn from_properties(properties: &[SgfToken]) -> Markup {
+        let mut markup = Markup::default();
+        for token in properties {
+            match token {
+                SgfToken::Square { coordinate } => markup.squares.push(from_coordinate(*coordinate)),
+                SgfToken::Triangle { coordinate } => markup.triangles.push(from_coordinate(*coordinate)),
+                SgfToken::Label { label, coordinate } => {
+                    markup.labels.push((from_coordinate(*coordinate), unescape_text(label)))
+                }
+                // sgf_parser reads CR as the FF[1] copyright property.
+                SgfToken::Copyright(value) => markup.circles.extend(points(value)),
+                SgfToken::Unknown((ident, value)) | SgfToken::Invalid((ident, value)) => {
+                    markup.add_property(ident, value)
+                }
+                _ => {}
+            }
+        }
+        markup
+    }
+
+    fn add_property(&mut self, ident: &str, value: &str) {
+        match ident {
+            "SQ" => self.squares.extend(points(value)),
+            "TR" => self.triangles.extend(points(value)),
+            "MA" => self.crosses.extend(points(value)),
+            "SL" => self.selected.extend(points(value)),
+            "DD" => self.dimmed.extend(points(value)),
+            "AR" => self.arrows.extend(point_pair(value)),
+            "LN" => self.lines.extend(point_pair(value)),
+            "LB" => {
+                if let Some((point, label)) = value.split_once(':') {
+                    if let [point] = points(point)[..] {
+                        self.labels.push((point, unescape_text(label)));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn set_dimmed(&mut self, dimmed: Vec<Intersection>) {
+        self.dimmed = dimmed;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Markup::default()
+    }
+
+    pub fn get_circles(&self) -> Vec<Intersection> {
+        self.circles.clone()
+    }
+
+    pub fn get_squares(&self) -> Vec<Intersection> {
+        self.squares.clone()
+    }
+
+    pub fn get_triangles(&self) -> Vec<Intersection> {
+        self.triangles.clone()
+    }
+
+    /// Points marked with an X (MA).
+    pub fn get_crosses(&self) -> Vec<Intersection> {
+        self.crosses.clone()
+    }
+
+    pub fn get_selected(&self) -> Vec<Intersection> {
+        self.selected.clone()
+    }
+
+    pub fn get_labels(&self) -> Vec<(Intersection, String)> {
+        self.labels.clone()
+    }
+
+    /// Arrows as (from, to) pairs.
+    pub fn get_arrows(&self) -> Vec<(Intersection, Intersection)> {
+        self.arrows.clone()
+    }
+
+    pub fn get_lines(&self) -> Vec<(Intersection, Intersection)> {
+        self.lines.clone()
+    }
+
+    /// Points shown greyed out. `Game::get_markup` carries DD over from
+    /// earlier nodes, `Node::get_markup` only has the node's own.
+    pub fn get_dimmed(&self) -> Vec<Intersection> {
+        self.dimmed.clone()
+    }
+
+    // The symbol drawn on a point for the ASCII board, if any.
+    pub(crate) fn ascii_symbol(&self, point: Intersection) -> Option<char> {
+        if let Some((_, label)) = self.labels.iter().find(|(p, _)| *p == point) {
+            return label.chars().next();
+        }
+        [
+            (&self.circles, '@'),
+            (&self.squares, '#'),
+            (&self.triangles, '^'),
+            (&self.crosses, 'x'),
+            (&self.selected, '*'),
+            (&self.dimmed, '~'),
+        ]
+        .iter()
+        .find(|(points, _)| points.contains(&point))
+        .map(|(_, symbol)| *symbol)
+    }
+
+    // The igo symbol drawn on a point for the LaTeX board, if any.
+    pub(crate) fn latex_symbol(&self, point: Intersection) -> Option<String> {
+        if let Some((_, label)) = self.labels.iter().find(|(p, _)| *p == point) {
+            return Some(latex_escape(label));
+        }
+        [
+            (&self.circles, "\\igocircle"),
+            (&self.squares, "\\igosquare"),
+            (&self.triangles, "\\igotriangle"),
+            (&self.crosses, "\\igocross"),
+        ]
+        .iter()
+        .find(|(points, _)| points.contains(&point))
+        .map(|(_, symbol)| symbol.to_string())
+    }
+}
+
+fn from_coordinate(coordinate: (u8, u8)) -> Intersection {
+    Intersection::from_sgf(coordinate.1.into(), coordinate.0.into())
+}
+
+fn points(value: &str) -> Vec<Intersection> {
+    point_list(value).unwrap_or_default()
+}
+
+// The two points of an AR or LN value "aa:cc".
+fn point_pair(value: &str) -> Option<(Intersection, Intersection)> {
+    let (from, to) = value.split_once(':')?;
+    match (&points(from)[..], &points(to)[..]) {
+        ([from], [to]) => Some((*from, *to)),
+        _ => None,
+    }
+}
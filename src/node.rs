@@ -0,0 +1,142 @@
+use sgf_parser::Color;
+use sgf_parser::SgfToken;
+
+use super::Emphasis;
+use super::Markup;
+use super::Move;
+use super::MoveAnnotation;
+use super::PositionAnnotation;
+use super::game_info::unescape_text;
+
+/// Identifies a node of a `Game`. The board at a node has the same number,
+/// see `Game::get_board`.
+pub type NodeId = u32;
+
+/// A node of the game tree, mirroring a node of the SGF record.
+#[derive(Debug, Clone, Default)]
+pub struct Node {
+    properties: Vec<SgfToken>,
+    mv: Option<Move>,
+    children: Vec<NodeId>,
+    parent: Option<NodeId>,
+}
+
+impl Node {
+    pub(crate) fn new(properties: Vec<SgfToken>, mv: Option<Move>, parent: Option<NodeId>) -> Node {
+        Node {
+            properties,
+            mv,
+            children: vec![],
+            parent,
+        }
+    }
+
+    /// The node's SGF properties as read by sgf_parser.
+    pub fn get_properties(&self) -> Vec<SgfToken> {
+        self.properties.clone()
+    }
+
+    /// The commentary in the node's C property.
+    pub fn get_comment(&self) -> Option<String> {
+        self.properties.iter().find_map(|token| match token {
+            SgfToken::Comment(comment) => Some(unescape_text(comment)),
+            _ => None,
+        })
+    }
+
+    /// The node's name from its N property, e.g. "Tesuji" or "Variation A".
+    pub fn get_name(&self) -> Option<String> {
+        self.properties.iter().find_map(|token| match token {
+            SgfToken::Unknown((ident, name)) if ident == "N" => Some(unescape_text(name)),
+            _ => None,
+        })
+    }
+
+    /// The BM, TE, DO or IT annotation of the node's move.
+    pub fn get_move_annotation(&self) -> Option<MoveAnnotation> {
+        self.properties.iter().find_map(|token| match token {
+            SgfToken::Unknown((ident, value)) => MoveAnnotation::from_sgf(ident, value),
+            _ => None,
+        })
+    }
+
+    /// The GB, GW, DM or UC annotation of the node's position.
+    pub fn get_position_annotation(&self) -> Option<PositionAnnotation> {
+        self.properties.iter().find_map(|token| match token {
+            SgfToken::Unknown((ident, value)) => PositionAnnotation::from_sgf(ident, value),
+            _ => None,
+        })
+    }
+
+    /// Set by the HO property when the node is a hotspot, such as a game-deciding move.
+    pub fn get_hotspot(&self) -> Option<Emphasis> {
+        self.properties.iter().find_map(|token| match token {
+            SgfToken::Unknown((ident, value)) if ident == "HO" => Some(Emphasis::from_sgf(value)),
+            _ => None,
+        })
+    }
+
+    /// The estimated score of the position from the V property, positive
+    /// when black is ahead.
+    pub fn get_value(&self) -> Option<f32> {
+        self.properties.iter().find_map(|token| match token {
+            SgfToken::Unknown((ident, value)) if ident == "V" => value.trim().parse().ok(),
+            _ => None,
+        })
+    }
+
+    /// The node's own markup, see also `Game::get_markup`.
+    pub fn get_markup(&self) -> Markup {
+        Markup::from_properties(&self.properties)
+    }
+
+    /// Seconds left on the player's clock from BL or WL, usually recorded
+    /// after the player's move.
+    pub fn get_time_left(&self, color: Color) -> Option<f32> {
+        let ident = match color {
+            Color::Black => "BL",
+            Color::White => "WL",
+        };
+        self.properties.iter().find_map(|token| match token {
+            SgfToken::Time { color: c, time } if *c == color => Some(*time as f32),
+            // sgf_parser only reads whole seconds.
+            SgfToken::Invalid((i, value)) if i == ident => value.trim().parse().ok(),
+            _ => None,
+        })
+    }
+
+    /// Moves left to play in the current overtime period, from OB or OW.
+    pub fn get_overtime_moves(&self, color: Color) -> Option<u32> {
+        self.properties.iter().find_map(|token| match token {
+            SgfToken::MovesRemaining { color: c, moves } if *c == color => Some(*moves),
+            _ => None,
+        })
+    }
+
+    /// The move played at this node, if any.
+    pub fn get_move(&self) -> Option<Move> {
+        self.mv.clone()
+    }
+
+    pub fn get_children(&self) -> Vec<NodeId> {
+        self.children.clone()
+    }
+
+    /// The child starting the given variation, 0 being the main line.
+    pub fn get_child(&self, variation: u32) -> Option<NodeId> {
+        self.children.get(variation as usize).copied()
+    }
+
+    pub fn get_variation_count(&self) -> u32 {
+        self.children.len() as u32
+    }
+
+    /// The parent node, None for the root.
+    pub fn get_parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub(crate) fn add_child(&mut self, child: NodeId) {
+        self.children.push(child);
+    }
+}
@@ -0,0 +1,165 @@
+use sgf_parser::RuleSet;
+
+use super::ScoringMethod;
+
+/// Which full-board repetitions `Game::place_stone` rejects, on top of simple ko.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SuperkoRule {
+    /// Only the immediate ko recapture is forbidden.
+    #[default]
+    None,
+    /// A move may not recreate any earlier board position in its line of play.
+    Positional,
+    /// A move may not recreate an earlier board position with the same player to move.
+    Situational,
+}
+
+/// Whether a move that leaves its own group without liberties is allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SuicideRule {
+    /// Suicide is an invalid move (Japanese, Chinese).
+    #[default]
+    Forbidden,
+    /// The suicidal group is removed from the board (New Zealand, Tromp-Taylor).
+    Allowed,
+}
+
+/// The rules a game is played under, bundling ko, suicide, scoring and komi.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Ruleset {
+    #[default]
+    Japanese,
+    Chinese,
+    Aga,
+    NewZealand,
+    Ing,
+    TrompTaylor,
+    Custom {
+        name: String,
+        superko_rule: SuperkoRule,
+        suicide_rule: SuicideRule,
+        scoring_method: ScoringMethod,
+        komi: f32,
+        removes_dead_stones: bool,
+    },
+}
+
+impl Ruleset {
+    /// The ruleset for an SGF `RU` value. Unrecognised names keep the name
+    /// and play by Japanese rules.
+    pub fn from_sgf(rule: &RuleSet) -> Ruleset {
+        match rule {
+            RuleSet::Japanese => Ruleset::Japanese,
+            RuleSet::Chinese => Ruleset::Chinese,
+            RuleSet::AGA => Ruleset::Aga,
+            RuleSet::NZ => Ruleset::NewZealand,
+            RuleSet::GOE => Ruleset::Ing,
+            RuleSet::Unknown(name) => match name.to_lowercase().as_str() {
+                "japanese" | "jp" | "korean" => Ruleset::Japanese,
+                "chinese" | "cn" => Ruleset::Chinese,
+                "aga" => Ruleset::Aga,
+                "nz" | "new zealand" => Ruleset::NewZealand,
+                "ing" | "goe" => Ruleset::Ing,
+                "tromp-taylor" | "tromp taylor" | "tt" => Ruleset::TrompTaylor,
+                _ => Ruleset::Japanese.customize(name),
+            },
+        }
+    }
+
+    /// The name written to an SGF `RU` property. Rulesets changed with
+    /// `with_superko_rule` or `with_suicide_rule` have an empty name, as SGF
+    /// has no way to state the change, and no RU is written for them.
+    pub fn get_name(&self) -> String {
+        match self {
+            Ruleset::Japanese => "Japanese".to_string(),
+            Ruleset::Chinese => "Chinese".to_string(),
+            Ruleset::Aga => "AGA".to_string(),
+            Ruleset::NewZealand => "NZ".to_string(),
+            Ruleset::Ing => "GOE".to_string(),
+            Ruleset::TrompTaylor => "Tromp-Taylor".to_string(),
+            Ruleset::Custom { name, .. } => name.clone(),
+        }
+    }
+
+    pub fn get_superko_rule(&self) -> SuperkoRule {
+        match self {
+            Ruleset::Japanese => SuperkoRule::None,
+            Ruleset::Chinese | Ruleset::Ing | Ruleset::TrompTaylor => SuperkoRule::Positional,
+            Ruleset::Aga | Ruleset::NewZealand => SuperkoRule::Situational,
+            Ruleset::Custom { superko_rule, .. } => *superko_rule,
+        }
+    }
+
+    pub fn get_suicide_rule(&self) -> SuicideRule {
+        match self {
+            Ruleset::Japanese | Ruleset::Chinese | Ruleset::Aga => SuicideRule::Forbidden,
+            Ruleset::NewZealand | Ruleset::Ing | Ruleset::TrompTaylor => SuicideRule::Allowed,
+            Ruleset::Custom { suicide_rule, .. } => *suicide_rule,
+        }
+    }
+
+    pub fn get_scoring_method(&self) -> ScoringMethod {
+        match self {
+            Ruleset::Japanese => ScoringMethod::Territory,
+            Ruleset::Chinese | Ruleset::Aga | Ruleset::NewZealand | Ruleset::Ing | Ruleset::TrompTaylor => {
+                ScoringMethod::Area
+            }
+            Ruleset::Custom { scoring_method, .. } => *scoring_method,
+        }
+    }
+
+    /// Whether the players agree on dead stones that are taken off before
+    /// counting. Tromp-Taylor counts the board as it stands.
+    pub fn removes_dead_stones(&self) -> bool {
+        match self {
+            Ruleset::TrompTaylor => false,
+            Ruleset::Custom { removes_dead_stones, .. } => *removes_dead_stones,
+            _ => true,
+        }
+    }
+
+    /// Komi for an even game when the record does not give one.
+    pub fn get_default_komi(&self) -> f32 {
+        match self {
+            Ruleset::Japanese => 6.5,
+            Ruleset::Chinese | Ruleset::Aga | Ruleset::TrompTaylor => 7.5,
+            Ruleset::NewZealand => 7.0,
+            Ruleset::Ing => 8.0,
+            Ruleset::Custom { komi, .. } => *komi,
+        }
+    }
+
+    /// A custom ruleset called name that otherwise behaves like this one.
+    pub fn customize(&self, name: &str) -> Ruleset {
+        Ruleset::Custom {
+            name: name.to_string(),
+            superko_rule: self.get_superko_rule(),
+            suicide_rule: self.get_suicide_rule(),
+            scoring_method: self.get_scoring_method(),
+            komi: self.get_default_komi(),
+            removes_dead_stones: self.removes_dead_stones(),
+        }
+    }
+
+    pub fn with_superko_rule(&self, superko_rule: SuperkoRule) -> Ruleset {
+        Ruleset::Custom {
+            name: String::new(),
+            superko_rule,
+            suicide_rule: self.get_suicide_rule(),
+            scoring_method: self.get_scoring_method(),
+            komi: self.get_default_komi(),
+            removes_dead_stones: self.removes_dead_stones(),
+        }
+    }
+
+    pub fn with_suicide_rule(&self, suicide_rule: SuicideRule) -> Ruleset {
+        Ruleset::Custom {
+            name: String::new(),
+            superko_rule: self.get_superko_rule(),
+            suicide_rule,
+            scoring_method: self.get_scoring_method(),
+            komi: self.get_default_komi(),
+            removes_dead_stones: self.removes_dead_stones(),
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use sgf_parser::*;
+use std::collections::BTreeSet;
+use std::collections::HashSet;
+
+use super::Board;
+use super::Intersection;
+use super::PointState;
+
+// A connected area of empty points and which colours border it.
+struct Region {
+    points: BTreeSet<Intersection>,
+    black_border: bool,
+    white_border: bool,
+}
+
+/// How a finished position is counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoringMethod {
+    /// Surrounded points plus prisoners (Japanese, Korean).
+    Territory,
+    /// Stones on the board plus surrounded points (Chinese, AGA, Tromp-Taylor).
+    Area,
+}
+
+/// The count of a finished position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Score {
+    method: ScoringMethod,
+    territory_black: BTreeSet<Intersection>,
+    territory_white: BTreeSet<Intersection>,
+    stones_black: u32,
+    stones_white: u32,
+    prisoners_black: u32,
+    prisoners_white: u32,
+    komi: f32,
+}
+
+impl Score {
+    pub fn get_method(&self) -> ScoringMethod {
+        self.method
+    }
+
+    /// Empty points and dead-stone points surrounded by black.
+    pub fn get_territory_black(&self) -> Vec<Intersection> {
+        self.territory_black.iter().cloned().collect()
+    }
+
+    /// Empty points and dead-stone points surrounded by white.
+    pub fn get_territory_white(&self) -> Vec<Intersection> {
+        self.territory_white.iter().cloned().collect()
+    }
+
+    /// White stones captured during the game plus white stones marked dead.
+    pub fn get_prisoners_black(&self) -> u32 {
+        self.prisoners_black
+    }
+
+    /// Black stones captured during the game plus black stones marked dead.
+    pub fn get_prisoners_white(&self) -> u32 {
+        self.prisoners_white
+    }
+
+    /// Black stones left on the board once dead stones are removed.
+    pub fn get_stones_black(&self) -> u32 {
+        self.stones_black
+    }
+
+    /// White stones left on the board once dead stones are removed.
+    pub fn get_stones_white(&self) -> u32 {
+        self.stones_white
+    }
+
+    pub fn get_komi(&self) -> f32 {
+        self.komi
+    }
+
+    pub fn get_black(&self) -> f32 {
+        let territory = self.territory_black.len() as u32;
+        match self.method {
+            ScoringMethod::Territory => (territory + self.prisoners_black) as f32,
+            ScoringMethod::Area => (territory + self.stones_black) as f32,
+        }
+    }
+
+    pub fn get_white(&self) -> f32 {
+        let territory = self.territory_white.len() as u32;
+        match self.method {
+            ScoringMethod::Territory => (territory + self.prisoners_white) as f32 + self.komi,
+            ScoringMethod::Area => (territory + self.stones_white) as f32 + self.komi,
+        }
+    }
+
+    /// The winning colour, None for a draw.
+    pub fn get_winner(&self) -> Option<Color> {
+        if self.get_black() > self.get_white() {
+            Some(Color::Black)
+        } else if self.get_white() > self.get_black() {
+            Some(Color::White)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_margin(&self) -> f32 {
+        (self.get_black() - self.get_white()).abs()
+    }
+
+    /// The result in SGF `RE` notation, e.g. "B+3.5" or "Draw".
+    pub fn to_result_string(&self) -> String {
+        match self.get_winner() {
+            Some(Color::Black) => format!("B+{}", self.get_margin()),
+            Some(Color::White) => format!("W+{}", self.get_margin()),
+            None => "Draw".to_string(),
+        }
+    }
+}
+
+impl Board {
+    /// Adds the group on intersection to dead_stones, or removes it if it is
+    /// already marked. Empty points are ignored.
+    pub fn mark_dead_group(&self, dead_stones: &mut BTreeSet<Intersection>, intersection: Intersection) {
+        if let Some(group) = self.get_group(intersection) {
+            let stones = group.get_stones();
+            if dead_stones.contains(&intersection) {
+                for stone in stones {
+                    dead_stones.remove(&stone);
+                }
+            } else {
+                dead_stones.extend(stones);
+            }
+        }
+    }
+
+    /// Counts the position with territory scoring (Japanese rules): surrounded
+    /// empty points plus prisoners, with dead_stones taken off as prisoners
+    /// first. Points touching both colours are neutral, and the eyes of groups
+    /// in seki are not territory.
+    pub fn score_territory(&self, dead_stones: &BTreeSet<Intersection>, komi: f32) -> Score {
+        self.count(dead_stones, komi, ScoringMethod::Territory)
+    }
+
+    /// Counts the position with area scoring (Chinese rules): stones on the
+    /// board plus empty points that reach only one colour, after removing
+    /// dead_stones. With no dead stones marked this is Tromp-Taylor scoring.
+    pub fn score_area(&self, dead_stones: &BTreeSet<Intersection>, komi: f32) -> Score {
+        self.count(dead_stones, komi, ScoringMethod::Area)
+    }
+
+    /// Counts the position with the scoring method of the board's ruleset.
+    pub fn score(&self, dead_stones: &BTreeSet<Intersection>, komi: f32) -> Score {
+        self.count(dead_stones, komi, self.get_ruleset().get_scoring_method())
+    }
+
+    fn count(&self, dead_stones: &BTreeSet<Intersection>, komi: f32, method: ScoringMethod) -> Score {
+        let mut board = self.clone();
+        let mut prisoners_black = self.get_prisoners_black();
+        let mut prisoners_white = self.get_prisoners_white();
+        for dead in dead_stones {
+            if let Ok(PointState::Filled {
+                move_number: _,
+                stone_color,
+            }) = self.get_point(dead.row, dead.col)
+            {
+                match stone_color {
+                    Color::Black => prisoners_white += 1,
+                    Color::White => prisoners_black += 1,
+                }
+                board.remove_stone(*dead);
+            }
+        }
+
+        let regions = board.empty_regions();
+        let seki = match method {
+            ScoringMethod::Territory => board.seki_stones(&regions),
+            ScoringMethod::Area => HashSet::new(),
+        };
+        let mut territory_black: BTreeSet<Intersection> = BTreeSet::new();
+        let mut territory_white: BTreeSet<Intersection> = BTreeSet::new();
+        for region in &regions {
+            let in_seki = region
+                .points
+                .iter()
+                .any(|point| board.neighbours(*point).iter().any(|n| seki.contains(n)));
+            if in_seki {
+                continue;
+            }
+            match (region.black_border, region.white_border) {
+                (true, false) => territory_black.extend(region.points.iter().cloned()),
+                (false, true) => territory_white.extend(region.points.iter().cloned()),
+                _ => {}
+            }
+        }
+
+        let mut stones_black = 0;
+        let mut stones_white = 0;
+        for r in 0..board.get_height() {
+            for c in 0..board.get_width() {
+                match board.get_point(r, c) {
+                    Ok(PointState::Filled {
+                        move_number: _,
+                        stone_color: Color::Black,
+                    }) => stones_black += 1,
+                    Ok(PointState::Filled {
+                        move_number: _,
+                        stone_color: Color::White,
+                    }) => stones_white += 1,
+                    _ => {}
+                }
+            }
+        }
+
+        Score {
+            method,
+            territory_black,
+            territory_white,
+            stones_black,
+            stones_white,
+            prisoners_black,
+            prisoners_white,
+            komi,
+        }
+    }
+
+    fn empty_regions(&self) -> Vec<Region> {
+        let mut ret: Vec<Region> = vec![];
+        let mut visited: HashSet<Intersection> = HashSet::new();
+        for r in 0..self.get_height() {
+            for c in 0..self.get_width() {
+                let start = Intersection::new(r, c);
+                if visited.contains(&start) || !matches!(self.get_point(r, c), Ok(PointState::Empty)) {
+                    continue;
+                }
+                let mut region = Region {
+                    points: BTreeSet::new(),
+                    black_border: false,
+                    white_border: false,
+                };
+                let mut pending = vec![start];
+                visited.insert(start);
+                while let Some(point) = pending.pop() {
+                    region.points.insert(point);
+                    for neighbour in self.neighbours(point) {
+                        match self.get_point(neighbour.row, neighbour.col) {
+                            Ok(PointState::Empty) => {
+                                if visited.insert(neighbour) {
+                                    pending.push(neighbour);
+                                }
+                            }
+                            Ok(PointState::Filled {
+                                move_number: _,
+                                stone_color: Color::Black,
+                            }) => region.black_border = true,
+                            Ok(PointState::Filled {
+                                move_number: _,
+                                stone_color: Color::White,
+                            }) => region.white_border = true,
+                            Err(_) => {}
+                        }
+                    }
+                }
+                ret.push(region);
+            }
+        }
+        ret
+    }
+
+    // Stones of groups in seki. A neutral point is a shared liberty of a seki
+    // when neither colour can fill it without putting its own stones in atari.
+    fn seki_stones(&self, regions: &[Region]) -> HashSet<Intersection> {
+        let mut ret: HashSet<Intersection> = HashSet::new();
+        for region in regions {
+            if !(region.black_border && region.white_border) {
+                continue;
+            }
+            for point in &region.points {
+                if self.can_approach(*point, Color::Black) || self.can_approach(*point, Color::White) {
+                    continue;
+                }
+                for neighbour in self.neighbours(*point) {
+                    if let Some(group) = self.get_group(neighbour) {
+                        ret.extend(group.get_stones());
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    fn can_approach(&self, intersection: Intersection, stone_color: Color) -> bool {
+        let mut board = self.clone();
+        board.place_stone(intersection, stone_color).is_ok() && board.get_liberty_count(intersection) > 1
+    }
+}